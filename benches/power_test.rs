@@ -0,0 +1,41 @@
+#![feature(test)]
+extern crate myprolog2;
+extern crate test;
+
+use myprolog2::theory::{ProveResult, Theory};
+use test::Bencher;
+
+// compare two commits with cargo +nightly bench --bench power_test on each, on
+// the same machine
+
+fn power_test() -> Theory {
+	let mut theory: Theory = Default::default();
+	theory
+		.add_string(include_str!("../examples/test/power_test"))
+		.unwrap();
+	theory
+}
+
+fn power(b: &mut Bencher, query: &str) {
+	let theory = power_test();
+	b.iter(|| assert_eq!(theory.query(query, 4096).unwrap().count(), 1));
+}
+
+// the whole search tree up to the depth limit, it has no answer
+#[bench]
+fn power_test_depth_exceed(b: &mut Bencher) {
+	let theory = power_test();
+	b.iter(|| assert!(matches!(theory.prove(16), ProveResult::DepthExceed)));
+}
+
+// 3^2 = 9
+#[bench]
+fn power_test_answer(b: &mut Bencher) {
+	power(b, "power(s(s(s(z))), s(s(z)), Z)");
+}
+
+// 3^4 = 81, unification of deep terms dominates
+#[bench]
+fn power_test_deep_answer(b: &mut Bencher) {
+	power(b, "power(s(s(s(z))), s(s(s(s(z)))), Z)");
+}
@@ -1,14 +1,14 @@
 extern crate myprolog2;
 extern crate rustyline;
 
-use myprolog2::theory::Theory;
+use myprolog2::theory::{ProveResult, Theory};
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 
 fn main() {
 	let axiom = include_str!("number");
 	let mut theory: Theory = Default::default();
-	theory.add_string(&axiom);
+	theory.add_string(axiom).unwrap();
 	theory.display();
 	let mut rl = Editor::<()>::new();
 	loop {
@@ -32,7 +32,7 @@ fn main() {
 			}
 		};
 		let mut state = true;
-		let mut s = "goal() :- ".to_string();
+		let mut s = String::new();
 		for each_item in line.split_whitespace() {
 			if state {
 				s += &format!("{}(", each_item);
@@ -59,10 +59,23 @@ fn main() {
 			}
 		}
 		s.pop();
-		s += ").";
+		s += ")";
 		println!("{}", s);
-		let mut theory1 = theory.clone();
-		theory1.add_string(&s);
-		println!("{:?}", theory1.prove(1_000_000));
+		let solutions = match theory.query(&s, 1_000_000) {
+			Ok(solutions) => solutions,
+			Err(error) => {
+				println!("{}", error);
+				continue;
+			}
+		};
+		match solutions.prove() {
+			ProveResult::Succeed(answer) => {
+				for (var, pred) in answer.iter() {
+					println!("{} = {}", var, pred);
+				}
+				println!("Succeed");
+			}
+			result => println!("{:?}", result),
+		}
 	}
 }
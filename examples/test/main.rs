@@ -1,20 +1,31 @@
 extern crate myprolog2;
 
-use std::io::prelude::*;
-
-use myprolog2::theory::Theory;
+use myprolog2::search_tree::SearchTree;
+use myprolog2::theory::{ProveResult, Theory};
+use myprolog2::tracer::ConsoleTracer;
 
 fn main() {
-	let arg = std::env::args()
-		.skip(1)
-		.next()
-		.unwrap_or("theory".to_string());
-	let mut string = "".to_string();
+	let arg = std::env::args().nth(1).unwrap_or("theory".to_string());
 	let f = std::fs::File::open(arg).unwrap();
-	let mut f = std::io::BufReader::new(f);
-	f.read_to_string(&mut string).unwrap();
 	let mut theory: Theory = Default::default();
-	theory.add_string(&string);
+	if let Err(error) = theory.add_reader(f) {
+		print!("{}", error);
+	}
+	// `test <theory> dot` prints the explored SLD tree in graphviz format
+	if std::env::args().nth(2).as_deref() == Some("dot") {
+		let mut tree: SearchTree = Default::default();
+		theory.prove_with_tracer(16, &mut tree);
+		print!("{}", tree.to_dot());
+		return;
+	}
 	theory.display();
-	println!("{:?}", theory.prove(16));
+	match theory.prove_with_tracer(16, &mut ConsoleTracer) {
+		ProveResult::Succeed(answer) => {
+			for (var, pred) in answer.iter() {
+				println!("{} = {}", var, theory.term_to_string(pred));
+			}
+			println!("Succeed");
+		}
+		result => println!("{:?}", result),
+	}
 }
@@ -0,0 +1,456 @@
+use std::cmp::Ordering;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{Pow, Signed, ToPrimitive, Zero};
+
+use crate::error::ProveErrorKind;
+use crate::pred::{Pred, Term};
+
+// value of a number term, integers are rationals with denominator 1,
+// floats are always finite
+#[derive(Clone, Debug, PartialEq)]
+pub enum Number {
+	Rational(BigRational),
+	Float(f64),
+}
+
+impl Number {
+	pub fn is_integer(&self) -> bool {
+		matches!(self, Number::Rational(value) if value.is_integer())
+	}
+
+	fn is_zero(&self) -> bool {
+		match self {
+			Number::Rational(value) => value.is_zero(),
+			Number::Float(value) => *value == 0.0,
+		}
+	}
+
+	fn to_f64(&self) -> f64 {
+		match self {
+			Number::Rational(value) => value.to_f64().unwrap_or(f64::NAN),
+			Number::Float(value) => *value,
+		}
+	}
+
+	// exact, a finite float is a rational with a power of two denominator
+	fn to_rational(&self) -> BigRational {
+		match self {
+			Number::Rational(value) => value.clone(),
+			Number::Float(value) => BigRational::from_float(*value).unwrap(),
+		}
+	}
+
+	// compares the values exactly, 1 =:= 1.0
+	pub fn value_cmp(&self, other: &Number) -> Ordering {
+		match (self, other) {
+			(Number::Float(a), Number::Float(b)) => a.partial_cmp(b).unwrap(),
+			_ => self.to_rational().cmp(&other.to_rational()),
+		}
+	}
+
+	// standard order of terms, by value and a float before an equal integer
+	pub fn standard_cmp(&self, other: &Number) -> Ordering {
+		self.value_cmp(other).then_with(|| match (self, other) {
+			(Number::Float(_), Number::Rational(_)) => Ordering::Less,
+			(Number::Rational(_), Number::Float(_)) => Ordering::Greater,
+			_ => Ordering::Equal,
+		})
+	}
+}
+
+impl From<BigInt> for Number {
+	fn from(value: BigInt) -> Number {
+		Number::Rational(BigRational::from_integer(value))
+	}
+}
+
+// floats are finite, so equality is reflexive
+impl Eq for Number {}
+
+impl std::hash::Hash for Number {
+	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+		match self {
+			Number::Rational(value) => value.hash(state),
+			// -0.0 == 0.0
+			Number::Float(value) => (value + 0.0).to_bits().hash(state),
+		}
+	}
+}
+
+impl std::ops::Neg for Number {
+	type Output = Number;
+
+	fn neg(self) -> Number {
+		match self {
+			Number::Rational(value) => Number::Rational(-value),
+			Number::Float(value) => Number::Float(-value),
+		}
+	}
+}
+
+// digits, optionally followed by r and the digits of a denominator, as in 1r3,
+// or a float with a fraction and an optional exponent, as in 1.0e-9
+pub(crate) fn parse_number(string: &str) -> Option<Number> {
+	let (negative, digits) = match string.strip_prefix('-') {
+		Some(digits) => (true, digits),
+		None => (false, string),
+	};
+	if !digits.starts_with(|ch: char| ch.is_ascii_digit()) {
+		return None;
+	}
+	if digits.contains('.') {
+		let value = digits.parse::<f64>().ok().filter(|x| x.is_finite())?;
+		return Some(Number::Float(if negative { -value } else { value }));
+	}
+	let mut split = digits.splitn(2, 'r');
+	let numerator = split.next().unwrap();
+	let denominator = split.next().unwrap_or("1");
+	let is_digits = |x: &str| !x.is_empty() && x.bytes().all(|ch| ch.is_ascii_digit());
+	if !is_digits(numerator) || !is_digits(denominator) {
+		return None;
+	}
+	let denominator: BigInt = denominator.parse().unwrap();
+	if denominator.is_zero() {
+		return None;
+	}
+	let value = BigRational::new(numerator.parse().unwrap(), denominator);
+	Some(Number::Rational(if negative { -value } else { value }))
+}
+
+// lowest terms, integers without denominator, floats always with a fraction
+pub(crate) fn number_to_string(value: &Number) -> String {
+	match value {
+		Number::Rational(value) if value.is_integer() => value.numer().to_string(),
+		Number::Rational(value) => format!("{}r{}", value.numer(), value.denom()),
+		Number::Float(value) => {
+			let string = format!("{:?}", value);
+			if string.contains('.') {
+				string
+			} else {
+				// 1e-9 is written 1.0e-9
+				let exponent = string.find('e').unwrap_or(string.len());
+				format!("{}.0{}", &string[..exponent], &string[exponent..])
+			}
+		}
+	}
+}
+
+fn is_evaluable(name: &str, arity: usize) -> bool {
+	match arity {
+		1 => matches!(
+			name,
+			"-" | "abs"
+				| "sqrt" | "sin"
+				| "cos" | "exp"
+				| "log" | "float"
+				| "truncate" | "round"
+				| "\\"
+		),
+		2 => {
+			matches!(
+				name,
+				"+" | "-"
+					| "*" | "/" | "//"
+					| "mod" | "rem" | "div"
+					| "min" | "max" | "**"
+					| "/\\" | "\\/" | "xor"
+					| "<<" | ">>"
+			)
+		}
+		_ => false,
+	}
+}
+
+// exact if both operands are rationals, a float otherwise
+fn binary(name: &str, a: &Number, b: &Number) -> Number {
+	if let (Number::Rational(a), Number::Rational(b)) = (a, b) {
+		return Number::Rational(match name {
+			"+" => a + b,
+			"-" => a - b,
+			"*" => a * b,
+			// exact, 1 / 3 is 1r3
+			_ => a / b,
+		});
+	}
+	let (a, b) = (a.to_f64(), b.to_f64());
+	Number::Float(match name {
+		"+" => a + b,
+		"-" => a - b,
+		"*" => a * b,
+		"/" => a / b,
+		_ => a.powf(b),
+	})
+}
+
+// bound on the size of the integers ** and << make, the others grow at most
+// by the size of their operands
+const MAX_BITS: u64 = 1 << 24;
+
+// the error comes with the id of the offending node
+fn evaluate_recurse(expr: &Pred, id: usize) -> Result<Number, (ProveErrorKind, usize)> {
+	let (name, arg_ids) = match &expr.nodes[id] {
+		Term::Number(value) => return Ok(value.clone()),
+		Term::Var(_) => return Err((ProveErrorKind::Instantiation, id)),
+		Term::Atom(name) => (name.name(), &[][..]),
+		Term::Compound(functor, args) => (functor.name().name(), args.as_slice()),
+		Term::Str(_) => return Err((ProveErrorKind::NotEvaluable, id)),
+	};
+	if !is_evaluable(name, arg_ids.len()) {
+		return Err((ProveErrorKind::NotEvaluable, id));
+	}
+	let args = arg_ids
+		.iter()
+		.map(|x| evaluate_recurse(expr, *x))
+		.collect::<Result<Vec<Number>, _>>()?;
+	if matches!(name, "/" | "//" | "mod" | "rem" | "div") && args[1].is_zero() {
+		return Err((ProveErrorKind::ZeroDivisor, id));
+	}
+	if matches!(
+		name,
+		"//" | "mod" | "rem" | "div" | "/\\" | "\\/" | "xor" | "<<" | ">>" | "\\"
+	) {
+		for (arg, arg_id) in args.iter().zip(arg_ids.iter()) {
+			if !arg.is_integer() {
+				return Err((ProveErrorKind::NotInteger, *arg_id));
+			}
+		}
+		let args: Vec<BigInt> = args.iter().map(|x| x.to_rational().to_integer()).collect();
+		return Ok(Number::from(match (name, args.as_slice()) {
+			// truncates toward zero, rem takes the sign of the dividend
+			("//", [a, b]) => a / b,
+			("rem", [a, b]) => a % b,
+			// floors, mod takes the sign of the divisor
+			("div", [a, b]) => a.div_floor(b),
+			("mod", [a, b]) => a.mod_floor(b),
+			// on the two's complement, \\ 5 is -6
+			("/\\", [a, b]) => a & b,
+			("\\/", [a, b]) => a | b,
+			("xor", [a, b]) => a ^ b,
+			("\\", [a]) => !a,
+			// a negative shift goes the other way, >> floors
+			(_, [a, b]) => {
+				let left = (name == "<<") != b.is_negative();
+				// shifting right by more than the size of a gives 0 or -1
+				let shift = match b.abs().to_u64() {
+					Some(shift) if !left => shift.min(a.bits()),
+					Some(shift) if a.is_zero() || a.bits() + shift <= MAX_BITS => shift,
+					_ if !left => a.bits(),
+					_ if a.is_zero() => 0,
+					_ => return Err((ProveErrorKind::IntegerOverflow, id)),
+				};
+				if left {
+					a << shift
+				} else {
+					a >> shift
+				}
+			}
+			_ => unreachable!(),
+		}));
+	}
+	let result = match (name, args.as_slice()) {
+		// integer powers stay exact, 2 ** -1 is 1r2
+		("**", [Number::Rational(a), Number::Rational(b)]) if b.is_integer() => {
+			let b = b.to_integer();
+			if b.is_negative() && a.is_zero() {
+				return Err((ProveErrorKind::ZeroDivisor, id));
+			}
+			// 0, 1 and -1 keep their size, only the sign and parity of b matter
+			let exponent = if a.is_integer() && a.numer().bits() <= 1 {
+				Some(b.signum().to_i32().unwrap() * if b.is_even() { 2 } else { 1 })
+			} else {
+				let bits = a.numer().bits().max(a.denom().bits());
+				b.to_i32()
+					.filter(|exponent| bits * exponent.unsigned_abs() as u64 <= MAX_BITS)
+			};
+			match exponent {
+				Some(exponent) => Number::Rational(Pow::pow(a, exponent)),
+				None => return Err((ProveErrorKind::IntegerOverflow, id)),
+			}
+		}
+		("+", [a, b]) | ("-", [a, b]) | ("*", [a, b]) | ("/", [a, b]) | ("**", [a, b]) => {
+			binary(name, a, b)
+		}
+		// an operand keeps its type, min(1, 1.0) is 1
+		("min", [a, b]) if b.value_cmp(a) == Ordering::Less => b.clone(),
+		("max", [a, b]) if b.value_cmp(a) == Ordering::Greater => b.clone(),
+		("min", [a, _]) | ("max", [a, _]) => a.clone(),
+		("-", [a]) => -a.clone(),
+		("abs", [Number::Rational(a)]) => Number::Rational(a.abs()),
+		("abs", [Number::Float(a)]) => Number::Float(a.abs()),
+		("float", [a]) => Number::Float(a.to_f64()),
+		("truncate", [a]) => Number::Rational(a.to_rational().trunc()),
+		// halves away from zero
+		("round", [a]) => Number::Rational(a.to_rational().round()),
+		("sqrt", [a]) => Number::Float(a.to_f64().sqrt()),
+		("sin", [a]) => Number::Float(a.to_f64().sin()),
+		("cos", [a]) => Number::Float(a.to_f64().cos()),
+		("exp", [a]) => Number::Float(a.to_f64().exp()),
+		("log", [a]) => Number::Float(a.to_f64().ln()),
+		_ => unreachable!(),
+	};
+	match result {
+		// sqrt(-1), log(0)
+		Number::Float(value) if value.is_nan() || (value.is_infinite() && name == "log") => {
+			Err((ProveErrorKind::Undefined, id))
+		}
+		Number::Float(value) if value.is_infinite() => Err((ProveErrorKind::FloatOverflow, id)),
+		result => Ok(result),
+	}
+}
+
+// value of an arithmetic expression, or the error and the offending subterm,
+// a cyclic term has no value
+pub(crate) fn evaluate(expr: &Pred) -> Result<Number, (ProveErrorKind, Pred)> {
+	if expr.is_cyclic() {
+		return Err((ProveErrorKind::NotEvaluable, expr.clone()));
+	}
+	evaluate_recurse(expr, expr.nodes.len() - 1).map_err(|(kind, id)| (kind, expr.subtree(id)))
+}
+
+pub(crate) fn compare(
+	name: &str,
+	left: &Pred,
+	right: &Pred,
+) -> Result<bool, (ProveErrorKind, Pred)> {
+	let ordering = evaluate(left)?.value_cmp(&evaluate(right)?);
+	Ok(match name {
+		"<" => ordering == Ordering::Less,
+		"=<" => ordering != Ordering::Greater,
+		">" => ordering == Ordering::Greater,
+		">=" => ordering != Ordering::Less,
+		"=:=" => ordering == Ordering::Equal,
+		"=\\=" => ordering != Ordering::Equal,
+		_ => unreachable!(),
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::clause::Clause;
+
+	fn expr(string: &str) -> Pred {
+		Clause::goals_from_string(&format!("f({})", string), 0)
+			.unwrap()
+			.0
+			.remove(0)
+			.args()
+			.remove(0)
+	}
+
+	#[test]
+	fn evaluate_integers() {
+		let value = |string| number_to_string(&evaluate(&expr(string)).unwrap());
+		assert_eq!(value("1 + 2 * 3"), "7");
+		assert_eq!(value("(1 + 2) * 3"), "9");
+		assert_eq!(value("10 - 4 - 3"), "3");
+		assert_eq!(value("-7 // 2"), "-3");
+		assert_eq!(value("-7 rem 2"), "-1");
+		assert_eq!(value("-7 mod 2"), "1");
+		assert_eq!(value("7 mod -2"), "-1");
+		assert_eq!(value("abs(3 - 5) + max(2, min(4, 3)) - -1"), "6");
+		assert_eq!(value("- (2 * 3)"), "-6");
+		assert_eq!(value("-7 div 2"), "-4");
+		assert_eq!(
+			value("12 /\\ 10 + (12 \\/ 10) * 100 + (12 xor 10) * 10000"),
+			"61408"
+		);
+		assert_eq!(value("\\ 5"), "-6");
+		assert_eq!(value("1 << 70 >> 68"), "4");
+		assert_eq!(value("-5 >> 1"), "-3");
+		assert_eq!(value("1 << -1 + 8 >> -2"), "32");
+		let error = |string| evaluate(&expr(string)).unwrap_err();
+		assert_eq!(
+			error("1 + X * 2"),
+			(ProveErrorKind::Instantiation, expr("X"))
+		);
+		assert_eq!(
+			error("1 + foo(2)"),
+			(ProveErrorKind::NotEvaluable, expr("foo(2)"))
+		);
+		assert_eq!(error("1 mod 0").0, ProveErrorKind::ZeroDivisor);
+		assert_eq!(error("1 div 0").0, ProveErrorKind::ZeroDivisor);
+		assert_eq!(error("1 << 2.0"), (ProveErrorKind::NotInteger, expr("2.0")));
+		assert_eq!(
+			error("7 // (1 / 2)"),
+			(ProveErrorKind::NotInteger, expr("1 / 2"))
+		);
+		assert_eq!(error("1 / (2 - 2)").0, ProveErrorKind::ZeroDivisor);
+		assert!(compare("<", &expr("1r3"), &expr("1 / 2")).unwrap());
+		assert!(compare("=<", &expr("1 + 1"), &expr("2")).unwrap());
+		assert!(!compare("=\\=", &expr("4 // 2"), &expr("2")).unwrap());
+	}
+
+	#[test]
+	fn evaluate_exact() {
+		let value = |string| number_to_string(&evaluate(&expr(string)).unwrap());
+		assert_eq!(
+			value("123456789012345678901234567890 * 1000000000000"),
+			"123456789012345678901234567890000000000000"
+		);
+		assert_eq!(value("-9223372036854775808 - 1"), "-9223372036854775809");
+		assert_eq!(value("1 / 3 + 1r6"), "1r2");
+		assert_eq!(value("2r4 * 4"), "2");
+		assert_eq!(value("-1 / 3"), "-1r3");
+		assert_eq!(value("abs(-3r9) - 1"), "-2r3");
+		assert_eq!(parse_number("0012r0018"), parse_number("2r3"));
+		assert_eq!(parse_number("1r0"), None);
+		assert_eq!(parse_number("1r"), None);
+	}
+
+	#[test]
+	fn evaluate_floats() {
+		let value = |string| number_to_string(&evaluate(&expr(string)).unwrap());
+		assert_eq!(value("1.5 + 1"), "2.5");
+		assert_eq!(value("1 / 4 * 2.0"), "0.5");
+		assert_eq!(value("2.0 * 3"), "6.0");
+		assert_eq!(value("1.0e-9 * 2"), "2.0e-9");
+		assert_eq!(value("sqrt(16)"), "4.0");
+		assert_eq!(value("exp(0) + sin(0) + log(1)"), "1.0");
+		assert_eq!(value("float(1r4)"), "0.25");
+		assert_eq!(value("truncate(-2.7)"), "-2");
+		assert_eq!(value("round(2.5) + round(-2.5)"), "0");
+		assert_eq!(value("round(7r2)"), "4");
+		assert_eq!(value("2 ** 10"), "1024");
+		assert_eq!(value("2 ** -2"), "1r4");
+		assert_eq!(value("4 ** 0.5"), "2.0");
+		assert_eq!(value("min(1, 1.0)"), "1");
+		assert_eq!(value("max(2, 2.5)"), "2.5");
+		let error = |string| evaluate(&expr(string)).unwrap_err().0;
+		assert_eq!(error("sqrt(-1)"), ProveErrorKind::Undefined);
+		assert_eq!(error("log(0)"), ProveErrorKind::Undefined);
+		assert_eq!(error("1.0 / 0"), ProveErrorKind::ZeroDivisor);
+		assert_eq!(error("0 ** -1"), ProveErrorKind::ZeroDivisor);
+		assert_eq!(error("exp(1000)"), ProveErrorKind::FloatOverflow);
+		assert_eq!(error("2 ** 100000000000"), ProveErrorKind::IntegerOverflow);
+		assert_eq!(error("3 ** -20000000"), ProveErrorKind::IntegerOverflow);
+		assert_eq!(error("1 << 100000000"), ProveErrorKind::IntegerOverflow);
+		assert_eq!(
+			error("-1 >> -100000000000"),
+			ProveErrorKind::IntegerOverflow
+		);
+		assert_eq!(error("0 ** -100000000000"), ProveErrorKind::ZeroDivisor);
+		// small bases and right shifts stay small whatever the exponent
+		assert_eq!(value("(-1) ** 100000000001 + 1 ** -100000000000"), "0");
+		assert_eq!(value("0 ** 100000000000 + (0 << 100000000000)"), "0");
+		assert_eq!(value("5 >> 100000000000 + (-5 >> 100000000000)"), "-1");
+		assert_eq!(error("5.0 mod 2"), ProveErrorKind::NotInteger);
+		assert!(compare("=:=", &expr("1"), &expr("1.0")).unwrap());
+		assert!(compare("<", &expr("1r3"), &expr("0.3334")).unwrap());
+		// 0.1 is slightly above 1r10
+		assert!(compare(">", &expr("0.1"), &expr("1r10")).unwrap());
+		let standard = |a, b| {
+			let (a, b) = (parse_number(a).unwrap(), parse_number(b).unwrap());
+			a.standard_cmp(&b)
+		};
+		assert_eq!(standard("1.0", "1"), Ordering::Less);
+		assert_eq!(standard("1", "1.5"), Ordering::Less);
+		assert_eq!(standard("2", "1.5"), Ordering::Greater);
+		assert_eq!(parse_number("2.50"), Some(Number::Float(2.5)));
+		assert_eq!(parse_number(".5"), None);
+		assert_eq!(parse_number("1.0e999"), None);
+	}
+}
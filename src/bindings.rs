@@ -0,0 +1,350 @@
+use std::collections::HashSet;
+
+use crate::pred::{self, InstMap, Pred, Term, Var};
+
+// what unification does when a variable would be bound to a term containing it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OccursCheck {
+	// the binding is made, the term is cyclic
+	#[default]
+	False,
+	// unification fails
+	True,
+	// unification raises an error
+	Error,
+}
+
+// state of a store to undo to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Mark {
+	heap: usize,
+	trail: usize,
+	variables: usize,
+}
+
+// variables as mutable cells over one heap of terms, unification binds cells in
+// place and records them on the trail, undo unbinds them back to a mark
+#[derive(Clone, Debug, Default)]
+pub struct Bindings {
+	heap: Vec<Term>,
+	// the first node of its variable for a variable node, the node itself otherwise
+	cells: Vec<usize>,
+	// node a variable cell is bound to
+	bound: Vec<Option<usize>>,
+	// depth of the compounds being resolved, a cycle leads back to one of them
+	resolving: Vec<Option<usize>>,
+	trail: Vec<usize>,
+	// cell of each variable id added
+	cells_by_id: Vec<Option<usize>>,
+	// variables in order of first appearance
+	variables: Vec<Var>,
+	occurs_check: OccursCheck,
+}
+
+impl Bindings {
+	pub fn new(occurs_check: OccursCheck) -> Bindings {
+		Bindings {
+			occurs_check,
+			..Default::default()
+		}
+	}
+
+	pub fn mark(&self) -> Mark {
+		Mark {
+			heap: self.heap.len(),
+			trail: self.trail.len(),
+			variables: self.variables.len(),
+		}
+	}
+
+	// unbinds the cells bound and drops the terms added since mark
+	pub fn undo(&mut self, mark: Mark) {
+		for cell in self.trail.drain(mark.trail..) {
+			self.bound[cell] = None;
+		}
+		for var in self.variables.drain(mark.variables..) {
+			self.cells_by_id[var.id as usize] = None;
+		}
+		self.heap.truncate(mark.heap);
+		self.cells.truncate(mark.heap);
+		self.bound.truncate(mark.heap);
+		self.resolving.truncate(mark.heap);
+	}
+
+	pub fn clear(&mut self) {
+		self.undo(Default::default());
+	}
+
+	// copies pred to the heap, variables of the same id share a cell, returns the root
+	pub fn add(&mut self, pred: &Pred) -> usize {
+		let base = self.heap.len();
+		for term in pred.nodes.iter() {
+			let id = self.heap.len();
+			let cell = match term {
+				Term::Var(var) => {
+					let index = var.id as usize;
+					if index >= self.cells_by_id.len() {
+						self.cells_by_id.resize(index + 1, None);
+					}
+					match self.cells_by_id[index] {
+						Some(cell) => cell,
+						None => {
+							self.cells_by_id[index] = Some(id);
+							self.variables.push(*var);
+							id
+						}
+					}
+				}
+				_ => id,
+			};
+			self.heap.push(match term {
+				Term::Compound(functor, args) => {
+					Term::Compound(*functor, args.iter().map(|x| x + base).collect())
+				}
+				term => term.clone(),
+			});
+			self.cells.push(cell);
+			self.bound.push(None);
+			self.resolving.push(None);
+		}
+		self.heap.len() - 1
+	}
+
+	// cell of the variable
+	pub fn var(&self, var: Var) -> Option<usize> {
+		self.cells_by_id.get(var.id as usize).copied().flatten()
+	}
+
+	// the node id stands for, an unbound variable or a non-variable term
+	pub fn term(&self, id: usize) -> &Term {
+		&self.heap[self.deref(id)]
+	}
+
+	// the unbound cell or the non-variable node id stands for
+	fn deref(&self, mut id: usize) -> usize {
+		while let Term::Var(_) = self.heap[id] {
+			match self.bound[self.cells[id]] {
+				None => return self.cells[id],
+				Some(value) => id = value,
+			}
+		}
+		id
+	}
+
+	// the unbound cell appears in the term at id
+	fn occurs(&self, cell: usize, id: usize) -> bool {
+		let mut pending = vec![id];
+		let mut visited = HashSet::new();
+		while let Some(id) = pending.pop() {
+			let id = self.deref(id);
+			if id == cell {
+				return true;
+			}
+			if visited.insert(id) {
+				pending.extend(self.heap[id].args());
+			}
+		}
+		false
+	}
+
+	// binds cell to the non-variable value under the occurs check policy
+	fn bind_checked(&mut self, cell: usize, value: usize) -> Result<bool, Pred> {
+		if self.occurs_check != OccursCheck::False && self.occurs(cell, value) {
+			return match self.occurs_check {
+				OccursCheck::Error => Err(self.resolve(value)),
+				_ => Ok(false),
+			};
+		}
+		self.bind(cell, value);
+		Ok(true)
+	}
+
+	fn bind(&mut self, cell: usize, value: usize) {
+		self.bound[cell] = Some(value);
+		self.trail.push(cell);
+	}
+
+	// Err with the term a variable occurs in when the occurs check raises errors,
+	// bindings made before a failure stay until undone
+	pub fn unify(&mut self, a: usize, b: usize) -> Result<bool, Pred> {
+		let mut pending = vec![(a, b)];
+		// more pairs of compounds than nodes may come from a cycle, from then on a
+		// pair met again is taken as unified, which ends the descent into cycles
+		let mut steps = 0;
+		let mut seen = HashSet::new();
+		while let Some((a, b)) = pending.pop() {
+			let (a, b) = (self.deref(a), self.deref(b));
+			if a == b {
+				continue;
+			}
+			match (&self.heap[a], &self.heap[b]) {
+				// the later variable is bound to the earlier one
+				(Term::Var(_), Term::Var(_)) => self.bind(a.max(b), a.min(b)),
+				(Term::Var(_), _) => {
+					if !self.bind_checked(a, b)? {
+						return Ok(false);
+					}
+				}
+				(_, Term::Var(_)) => {
+					if !self.bind_checked(b, a)? {
+						return Ok(false);
+					}
+				}
+				(Term::Compound(a_functor, a_args), Term::Compound(b_functor, b_args)) => {
+					if a_functor != b_functor {
+						return Ok(false);
+					}
+					steps += 1;
+					if steps > self.heap.len() && !seen.insert((a, b)) {
+						continue;
+					}
+					pending.extend(a_args.iter().copied().zip(b_args.iter().copied()).rev());
+				}
+				(a_term, b_term) => {
+					if a_term != b_term {
+						return Ok(false);
+					}
+				}
+			}
+		}
+		Ok(true)
+	}
+
+	// the term at id with its bindings substituted, a cyclic term if a variable
+	// is bound to a term containing it
+	pub fn resolve(&mut self, id: usize) -> Pred {
+		let mut result: Pred = Default::default();
+		self.resolve_recurse(&mut result, &mut Vec::new(), id);
+		result
+	}
+
+	// referenced holds whether a back reference points to the compound at each depth
+	fn resolve_recurse(
+		&mut self,
+		target: &mut Pred,
+		referenced: &mut Vec<bool>,
+		id: usize,
+	) -> usize {
+		let node = self.deref(id);
+		let functor = match &self.heap[node] {
+			Term::Compound(functor, _) => *functor,
+			term => return target.push(term.clone()),
+		};
+		if let Some(depth) = self.resolving[node] {
+			referenced[depth] = true;
+			return pred::back_reference(depth);
+		}
+		let start = target.nodes.len();
+		let depth = referenced.len();
+		self.resolving[node] = Some(depth);
+		referenced.push(false);
+		let arity = self.heap[node].args().len();
+		let mut args = Vec::with_capacity(arity);
+		for i in 0..arity {
+			let arg = self.heap[node].args()[i];
+			args.push(self.resolve_recurse(target, referenced, arg));
+		}
+		self.resolving[node] = None;
+		let result = target.push(Term::Compound(functor, args));
+		if referenced.pop().unwrap() {
+			target.resolve_back_references(start, depth, result);
+		}
+		result
+	}
+
+	// pred with the variables of the store substituted
+	pub fn instantiate(&mut self, pred: &Pred) -> Pred {
+		let mark = self.mark();
+		let root = self.add(pred);
+		let result = self.resolve(root);
+		self.undo(mark);
+		result
+	}
+
+	// the variables bound from one mark to a later one and their values now
+	pub fn bound_between(&mut self, from: Mark, to: Mark) -> InstMap {
+		let cells: Vec<usize> = self.trail[from.trail..to.trail].to_vec();
+		cells
+			.into_iter()
+			.map(|cell| {
+				let var = self.heap[cell].get_var().unwrap();
+				(var, self.resolve(cell))
+			})
+			.collect()
+	}
+
+	// the bound variables and their resolved values
+	pub fn instmap(&mut self) -> InstMap {
+		let bound: Vec<(Var, usize)> = self
+			.variables
+			.iter()
+			.map(|var| (*var, self.var(*var).unwrap()))
+			.filter(|(_, cell)| self.bound[*cell].is_some())
+			.collect();
+		bound
+			.into_iter()
+			.map(|(var, cell)| (var, self.resolve(cell)))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::clause::Clause;
+	use crate::symbol::Symbol;
+
+	#[test]
+	fn bindings_undo() {
+		let (clause, _) = Clause::from_string("p(X, Y, Z) :- p(Y, f(Z), a)", 0).unwrap();
+		let mut bindings: Bindings = Default::default();
+		let target = bindings.add(&clause.body[0]);
+		let mark = bindings.mark();
+		let head = bindings.add(&clause.head);
+		assert_eq!(bindings.unify(head, target), Ok(true));
+		assert_eq!(bindings.resolve(head).to_string(), "p(f(a), f(a), a)");
+		let instmap = bindings.instmap();
+		let var = |name: &str| {
+			let vars = clause.head.variables();
+			vars.into_iter().find(|x| x.to_string() == name).unwrap()
+		};
+		assert_eq!(instmap[&var("X")].to_string(), "f(a)");
+		assert_eq!(instmap[&var("Y")].to_string(), "f(a)");
+		let end = bindings.mark();
+		assert_eq!(bindings.bound_between(mark, end), instmap);
+		let z = bindings.var(var("Z")).unwrap();
+		assert_eq!(bindings.term(z), &Term::Atom(Symbol::intern("a")));
+		bindings.undo(mark);
+		assert!(bindings.instmap().is_empty());
+		assert_eq!(bindings.resolve(target).to_string(), "p(Y, f(Z), a)");
+		// a failed attempt is undone the same way
+		let (clause, _) = Clause::from_string("p(b, Y, b)", 0).unwrap();
+		let head = bindings.add(&clause.head);
+		assert_eq!(bindings.unify(head, target), Ok(false));
+		bindings.undo(mark);
+		assert_eq!(bindings.mark(), mark);
+		assert_eq!(bindings.instantiate(&clause.head).to_string(), "p(b, Y, b)");
+	}
+
+	#[test]
+	fn bindings_occurs_check() {
+		let (clause, _) = Clause::from_string("p(X, f(X)) :- p(Y, Y)", 0).unwrap();
+		let unify = |occurs_check| {
+			let mut bindings = Bindings::new(occurs_check);
+			let head = bindings.add(&clause.head);
+			let target = bindings.add(&clause.body[0]);
+			bindings
+				.unify(head, target)
+				.map_err(|culprit| culprit.to_string())
+		};
+		assert_eq!(unify(OccursCheck::False), Ok(true));
+		assert_eq!(unify(OccursCheck::True), Ok(false));
+		assert_eq!(unify(OccursCheck::Error), Err("f(X)".to_string()));
+		// a variable bound to itself through other variables is no cycle
+		let (clause, _) = Clause::from_string("p(X, Y, Y) :- p(Y, X, Z)", 0).unwrap();
+		let mut bindings = Bindings::new(OccursCheck::True);
+		let head = bindings.add(&clause.head);
+		let target = bindings.add(&clause.body[0]);
+		assert_eq!(bindings.unify(head, target), Ok(true));
+	}
+}
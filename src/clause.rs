@@ -1,7 +1,64 @@
 use std::collections::{HashMap, VecDeque};
 
+use num_bigint::BigInt;
+
+use crate::arith;
+use crate::bindings::Bindings;
+use crate::error::{ParseError, ParseErrorKind, Span};
+use crate::operator::{self, Operators, ARGUMENT_PRIORITY, TERM_PRIORITY};
 use crate::pred::InstMap;
-use crate::pred::Pred;
+use crate::pred::{Pred, Term, Var};
+use crate::quote;
+use crate::reader;
+use crate::symbol::{Functor, Symbol};
+
+// what a double-quoted text stands for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DoubleQuotes {
+	// list of character codes
+	#[default]
+	Codes,
+	// list of one-character atoms
+	Chars,
+	// string object, printed in double quotes
+	String,
+}
+
+// flags and operators changing how clauses are read
+#[derive(Clone, Debug, Default)]
+pub struct Syntax {
+	pub double_quotes: DoubleQuotes,
+	pub operators: Operators,
+}
+
+// numbers the variables of a clause in order of appearance from next on, a
+// name read again is the same variable and each _ a new one
+struct Numbering {
+	next: u32,
+	named: HashMap<String, Var>,
+}
+
+impl Numbering {
+	fn var(&mut self, ident: &str) -> Var {
+		if let Some(var) = self.named.get(ident) {
+			return *var;
+		}
+		let var = Var {
+			id: self.next,
+			name: None,
+		};
+		self.next += 1;
+		if ident == "_" {
+			return var;
+		}
+		let var = Var {
+			name: Some(Symbol::intern(ident)),
+			..var
+		};
+		self.named.insert(ident.to_string(), var);
+		var
+	}
+}
 
 #[derive(Clone, Debug, Default)]
 pub struct Clause {
@@ -11,190 +68,1016 @@ pub struct Clause {
 
 impl std::fmt::Display for Clause {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		let mut result = self.head.to_string();
+		write!(f, "{}", self.to_string_with(operator::standard()))
+	}
+}
+
+impl Clause {
+	pub fn get_name(&self) -> String {
+		self.head.get_name()
+	}
+
+	pub fn to_string_with(&self, operators: &Operators) -> String {
+		let mut result = self.head.to_string_with(operators);
 		if !self.body.is_empty() {
 			result += " :- ";
 			for body in self.body.iter() {
-				result += &body.to_string();
+				result += &body.to_string_with(operators);
 				result += ", "
 			}
 			result.pop();
 			result.pop();
 		}
-		write!(f, "{}", result)
+		result
 	}
-}
 
-impl Clause {
-	pub fn get_name(&self) -> String {
-		self.head.nodes.last().unwrap().ident.clone()
+	pub fn from_string(string: &str, suffix_alloc_id: u32) -> Result<(Clause, u32), ParseError> {
+		Clause::from_string_with(string, suffix_alloc_id, &Default::default())
+	}
+
+	pub fn from_string_with(
+		string: &str,
+		suffix_alloc_id: u32,
+		syntax: &Syntax,
+	) -> Result<(Clause, u32), ParseError> {
+		Clause::parse(string, suffix_alloc_id, true, syntax)
 	}
 
-	pub fn from_string(string: &str, mut suffix_alloc_id: u32) -> (Clause, u32) {
+	// goals of a query, a clause body without head
+	pub fn goals_from_string(
+		string: &str,
+		suffix_alloc_id: u32,
+	) -> Result<(Vec<Pred>, u32), ParseError> {
+		Clause::goals_from_string_with(string, suffix_alloc_id, &Default::default())
+	}
+
+	pub fn goals_from_string_with(
+		string: &str,
+		suffix_alloc_id: u32,
+		syntax: &Syntax,
+	) -> Result<(Vec<Pred>, u32), ParseError> {
+		let (clause, suffix_alloc_id) = Clause::parse(string, suffix_alloc_id, false, syntax)?;
+		Ok((clause.body, suffix_alloc_id))
+	}
+
+	fn parse(
+		string: &str,
+		suffix_alloc_id: u32,
+		head: bool,
+		syntax: &Syntax,
+	) -> Result<(Clause, u32), ParseError> {
 		use plex::lexer;
 		pub enum TokenOrUnit {
 			Ident(String),
+			// quoted atom, never an operator
+			Quoted(String),
 			Whitespace,
+			// a character no token starts with
+			Invalid,
 			LeftParenthesis,
 			RightParenthesis,
 			Unit(usize),
+			// left parenthesis opening a parenthesized term
+			Group,
+			ListOpen,
+			ListClose,
+			// separates the elements of a list from its tail
+			Bar,
+		}
+
+		type Token = (TokenOrUnit, usize, usize);
+		// kind and byte range
+		type Error = (ParseErrorKind, usize, usize);
+
+		// operands and operators of a term, in order
+		enum Elem {
+			Term(TokenOrUnit),
+			Infix(String, usize, usize),
+			Prefix(String),
+			Postfix(String, usize, usize),
+		}
+
+		fn missing(operators: &Operators, operator: &str, start: usize, end: usize) -> Error {
+			match operators.infix(operator) {
+				Some((priority, _)) if priority > 1000 => (ParseErrorKind::MissingGoal, start, end),
+				_ => (ParseErrorKind::MissingOperand, start, end),
+			}
+		}
+
+		// the token after an operator may start its operand
+		fn operand_follows(operators: &Operators, next: Option<&Token>) -> bool {
+			match next {
+				None | Some((TokenOrUnit::Bar, _, _)) => false,
+				Some((TokenOrUnit::Ident(next), _, _)) => {
+					(operators.infix(next).is_none() && operators.postfix(next).is_none())
+						|| operators.prefix(next).is_some()
+				}
+				Some(_) => true,
+			}
+		}
+
+		// an operator is prefix before an operand, infix or postfix after one, an
+		// atom otherwise
+		fn classify(operators: &Operators, items: VecDeque<Token>) -> Result<Vec<Elem>, Error> {
+			let mut result = Vec::new();
+			let mut expect_term = true;
+			let mut items = items.into_iter().peekable();
+			while let Some((token, start, end)) = items.next() {
+				if let TokenOrUnit::Bar = token {
+					return Err((ParseErrorKind::DanglingIdentifier, start, end));
+				}
+				if !expect_term {
+					match token {
+						TokenOrUnit::Ident(ident)
+							if operators.postfix(&ident).is_some()
+								&& (operators.infix(&ident).is_none()
+									|| !operand_follows(operators, items.peek())) =>
+						{
+							result.push(Elem::Postfix(ident, start, end));
+						}
+						TokenOrUnit::Ident(ident) if operators.infix(&ident).is_some() => {
+							result.push(Elem::Infix(ident, start, end));
+							expect_term = true;
+						}
+						_ => return Err((ParseErrorKind::DanglingIdentifier, start, end)),
+					}
+					continue;
+				}
+				if let TokenOrUnit::Ident(ident) = &token {
+					let operand_follows = operand_follows(operators, items.peek());
+					// negative integer literal
+					if let Some((TokenOrUnit::Ident(next), next_start, _)) = items.peek() {
+						if ident == "-"
+							&& *next_start == end && next.starts_with(|ch: char| ch.is_ascii_digit())
+						{
+							let literal =
+								arith::number_to_string(&-arith::parse_number(next).unwrap());
+							result.push(Elem::Term(TokenOrUnit::Ident(literal)));
+							items.next();
+							expect_term = false;
+							continue;
+						}
+					}
+					if operand_follows && operators.prefix(ident).is_some() {
+						result.push(Elem::Prefix(ident.clone()));
+						continue;
+					}
+					if operand_follows && operators.infix(ident).is_some() {
+						return Err(missing(operators, ident, start, end));
+					}
+				}
+				result.push(Elem::Term(token));
+				expect_term = false;
+			}
+			if expect_term {
+				if let Some(Elem::Infix(ident, start, end)) = result.last() {
+					return Err(missing(operators, ident, *start, *end));
+				}
+			}
+			Ok(result)
+		}
+
+		// the spelling of a name token decides what it stands for
+		fn leaf(ident: &str, variables: &mut Numbering) -> Term {
+			if let Some(value) = arith::parse_number(ident) {
+				Term::Number(value)
+			} else if ident.starts_with(|ch: char| ch.is_ascii_uppercase() || ch == '_') {
+				Term::Var(variables.var(ident))
+			} else {
+				Term::Atom(Symbol::intern(ident))
+			}
+		}
+
+		// turn atoms and variables into nodes, reversed as the arguments used to be popped
+		// variables are numbered in reading order
+		fn convert(pred: &mut Pred, elems: &mut [Elem], variables: &mut Numbering) {
+			let terms: Vec<Option<Term>> = elems
+				.iter()
+				.map(|elem| match elem {
+					Elem::Term(TokenOrUnit::Ident(ident)) => Some(leaf(ident, variables)),
+					Elem::Term(TokenOrUnit::Quoted(ident)) => {
+						Some(Term::Atom(Symbol::intern(ident)))
+					}
+					_ => None,
+				})
+				.collect();
+			for (elem, term) in elems.iter_mut().zip(terms).rev() {
+				if let (Elem::Term(token), Some(term)) = (elem, term) {
+					*token = TokenOrUnit::Unit(pred.push(term));
+				}
+			}
+		}
+
+		// precedence climbing from elems[*pos], the node and priority of the
+		// longest term of priority at most max
+		fn climb(
+			operators: &Operators,
+			pred: &mut Pred,
+			elems: &[Elem],
+			pos: &mut usize,
+			max: usize,
+		) -> (usize, usize) {
+			*pos += 1;
+			let (mut left, mut left_priority) = match &elems[*pos - 1] {
+				Elem::Prefix(ident) => {
+					let (priority, fixity) = operators.prefix(ident).unwrap();
+					// binds as tight as the context needs, as most systems read it
+					let priority = priority.min(max);
+					let (_, operand_max) = fixity.operand_priorities(priority);
+					let (operand, _) = climb(operators, pred, elems, pos, operand_max);
+					(
+						pred.push(Term::Compound(
+							Functor::new(Symbol::intern(ident), 1),
+							vec![operand],
+						)),
+						priority,
+					)
+				}
+				Elem::Term(TokenOrUnit::Unit(id)) => (*id, 0),
+				_ => unreachable!(),
+			};
+			while let Some(elem) = elems.get(*pos) {
+				let (ident, (priority, fixity)) = match elem {
+					Elem::Infix(ident, _, _) => (ident, operators.infix(ident).unwrap()),
+					Elem::Postfix(ident, _, _) => (ident, operators.postfix(ident).unwrap()),
+					_ => unreachable!(),
+				};
+				let (left_max, right_max) = fixity.operand_priorities(priority);
+				if priority > max || left_priority > left_max {
+					break;
+				}
+				*pos += 1;
+				let mut args = vec![left];
+				if let Elem::Infix(_, _, _) = elem {
+					args.push(climb(operators, pred, elems, pos, right_max).0);
+				}
+				let functor = Functor::new(Symbol::intern(ident), args.len());
+				left = pred.push(Term::Compound(functor, args));
+				left_priority = priority;
+			}
+			(left, left_priority)
+		}
+
+		// the term of elems, an operator left over does not fit its operands
+		fn reduce(
+			operators: &Operators,
+			pred: &mut Pred,
+			elems: &[Elem],
+			max: usize,
+		) -> Result<usize, Error> {
+			let mut pos = 0;
+			let (id, _) = climb(operators, pred, elems, &mut pos, max);
+			match elems.get(pos) {
+				None => Ok(id),
+				Some(Elem::Infix(_, start, end) | Elem::Postfix(_, start, end)) => {
+					Err((ParseErrorKind::PriorityClash, *start, *end))
+				}
+				Some(_) => unreachable!(),
+			}
+		}
+
+		// a goal of the clause from the tokens since the last , or :-
+		fn finish_goal(
+			operators: &Operators,
+			token_stack: &mut Vec<Token>,
+			current_pred: &mut Pred,
+			variables: &mut Numbering,
+		) -> Result<Option<Pred>, Error> {
+			let items: VecDeque<Token> = std::mem::take(token_stack).into_iter().collect();
+			// a lone atom is a goal of arity 0, a lone variable or number is not
+			let lone = match items.front() {
+				Some((_, start, end)) if items.len() == 1 => Some((*start, *end)),
+				_ => None,
+			};
+			let mut elems = classify(operators, items)?;
+			if elems.is_empty() {
+				return Ok(None);
+			}
+			convert(current_pred, &mut elems, variables);
+			reduce(operators, current_pred, &elems, TERM_PRIORITY)?;
+			match lone {
+				Some((start, end)) if current_pred.root().symbol().is_none() => {
+					Err((ParseErrorKind::DanglingIdentifier, start, end))
+				}
+				_ => Ok(Some(std::mem::take(current_pred))),
+			}
+		}
+
+		fn push_goal(
+			result: &mut Clause,
+			pred_num: &mut usize,
+			pred: Option<Pred>,
+			start: usize,
+			end: usize,
+		) -> Result<(), Error> {
+			let pred = match pred {
+				None => return Ok(()),
+				Some(pred) => pred,
+			};
+			if *pred_num == 0 {
+				if !matches!(pred.root().symbol(), Some(name) if name.name() != "!") {
+					return Err((ParseErrorKind::InvalidPredicateName, start, end));
+				}
+				result.head = pred;
+			} else {
+				result.body.push(pred);
+			}
+			*pred_num += 1;
+			Ok(())
 		}
 
 		lexer! {
 			fn next_token(text: 'a) -> TokenOrUnit;
 
 			r#"[A-Za-z0-9_]+"# => TokenOrUnit::Ident(text.to_owned()),
+			r#"[0-9]+\.[0-9]+([eE][-+]?[0-9]+)?"# => TokenOrUnit::Ident(text.to_owned()),
+			r#"[-+*/\\^<>=~:?@#&$.]+"# => TokenOrUnit::Ident(text.to_owned()),
+			r#"[!;,]|\[\]|\{\}"# => TokenOrUnit::Ident(text.to_owned()),
+			r#"\["# => TokenOrUnit::ListOpen,
+			r#"\]"# => TokenOrUnit::ListClose,
+			r#"\|"# => TokenOrUnit::Bar,
 			r#"\("# => TokenOrUnit::LeftParenthesis,
 			r#"\)"# => TokenOrUnit::RightParenthesis,
-			r#"."# => TokenOrUnit::Whitespace,
+			r#"[ \t\r\n]"# => TokenOrUnit::Whitespace,
+			r#"."# => TokenOrUnit::Invalid,
 		}
 
+		let operators = &syntax.operators;
+		let error = |(kind, start, end): Error| ParseError {
+			kind,
+			clause: string.to_string(),
+			span: Span::new(string, start, end),
+		};
+
 		let mut result: Clause = Default::default();
 
 		let mut remaining = string;
-		let mut token_stack = Vec::new();
+		// tokens with their byte range
+		let mut token_stack: Vec<Token> = Vec::new();
 		let mut plevel: usize = 0;
-		let mut pred_num: usize = 0; // 0 is head
+		let mut pred_num: usize = if head { 0 } else { 1 }; // 0 is head
 		let mut current_pred: Pred = Default::default();
-		while let Some((token, new_remaining)) = next_token(&remaining) {
+		let mut variables = Numbering {
+			next: suffix_alloc_id,
+			named: HashMap::new(),
+		};
+		loop {
+			// comments separate tokens like layout
+			if let Some((len, closed)) = reader::comment_len(remaining) {
+				if !closed {
+					let start = string.len() - remaining.len();
+					return Err(error((
+						ParseErrorKind::UnterminatedComment,
+						start,
+						string.len(),
+					)));
+				}
+				remaining = &remaining[len..];
+				continue;
+			}
+			if let Some(quote) = remaining.chars().next().filter(|ch| "'\"`".contains(*ch)) {
+				let start = string.len() - remaining.len();
+				let (len, text) = quote::scan(remaining)
+					.map_err(|(kind, from, to)| error((kind, start + from, start + to)))?;
+				let token = match (quote, syntax.double_quotes) {
+					('\'', _) => TokenOrUnit::Quoted(text),
+					('"', DoubleQuotes::String) => {
+						TokenOrUnit::Unit(current_pred.push(Term::Str(text)))
+					}
+					('"', DoubleQuotes::Chars) => {
+						let chars = text
+							.chars()
+							.map(|ch| Term::Atom(Symbol::intern(&ch.to_string())))
+							.collect();
+						TokenOrUnit::Unit(current_pred.push_list(chars))
+					}
+					_ => {
+						let codes = text
+							.chars()
+							.map(|ch| Term::Number(BigInt::from(ch as u32).into()))
+							.collect();
+						TokenOrUnit::Unit(current_pred.push_list(codes))
+					}
+				};
+				token_stack.push((token, start, start + len));
+				remaining = &remaining[len..];
+				continue;
+			}
+			let (token, new_remaining) = match next_token(remaining) {
+				Some(token) => token,
+				None => break,
+			};
+			let start = string.len() - remaining.len();
+			let end = string.len() - new_remaining.len();
 			match token {
 				TokenOrUnit::Whitespace => {}
+				TokenOrUnit::Invalid => {
+					return Err(error((ParseErrorKind::InvalidCharacter, start, end)))
+				}
+				// the end token of a clause given with its terminating .
+				TokenOrUnit::Ident(ident)
+					if ident == "." && reader::skip_layout(new_remaining).is_empty() =>
+				{
+					break
+				}
+				TokenOrUnit::Ident(mut ident) => {
+					if ident.starts_with(|ch: char| ch.is_ascii_digit()) {
+						match arith::parse_number(&ident) {
+							Some(value) => ident = arith::number_to_string(&value),
+							None => {
+								return Err(error((ParseErrorKind::InvalidIdentifier, start, end)))
+							}
+						}
+					}
+					if plevel == 0 && (ident == "," || ident == ":-") {
+						let goal_start = token_stack.first().map_or(start, |token| token.1);
+						let goal = finish_goal(
+							operators,
+							&mut token_stack,
+							&mut current_pred,
+							&mut variables,
+						)
+						.map_err(error)?;
+						push_goal(&mut result, &mut pred_num, goal, goal_start, start)
+							.map_err(error)?;
+					} else {
+						token_stack.push((TokenOrUnit::Ident(ident), start, end));
+					}
+				}
 				TokenOrUnit::LeftParenthesis => {
+					// a name right before the parenthesis is a functor
+					let call = match token_stack.last() {
+						Some((TokenOrUnit::Ident(ident), _, ident_end)) => {
+							*ident_end == start || !operators.is_operator(ident)
+						}
+						Some((TokenOrUnit::Quoted(_), _, _)) => true,
+						_ => false,
+					};
+					if !call && plevel == 0 && pred_num == 0 {
+						return Err(error((ParseErrorKind::InvalidPredicateName, start, end)));
+					}
 					plevel += 1;
-					token_stack.push(TokenOrUnit::LeftParenthesis);
+					let token = if call {
+						TokenOrUnit::LeftParenthesis
+					} else {
+						TokenOrUnit::Group
+					};
+					token_stack.push((token, start, end));
 				}
 				TokenOrUnit::RightParenthesis => {
-					let mut id_list = VecDeque::new();
-					loop {
+					let mut items = VecDeque::new();
+					let (group, lp_start) = loop {
 						match token_stack.pop() {
-							Some(token) => match token {
-								TokenOrUnit::LeftParenthesis => {
-									match token_stack.pop() {
-										Some(TokenOrUnit::Ident(string)) => {
-											token_stack.push(TokenOrUnit::Unit(
-												current_pred.push_node(string, Vec::from(id_list)),
-											));
-										}
-										_ => {
-											panic!("Invalid predicate name!");
-										}
-									}
-									plevel -= 1;
-									if plevel == 0 {
-										if pred_num == 0 {
-											result.head = current_pred;
-										} else {
-											result.body.push(current_pred);
-										}
-										pred_num += 1;
-										current_pred = Default::default();
-									}
+							Some((TokenOrUnit::LeftParenthesis, lp_start, _)) => {
+								break (false, lp_start)
+							}
+							Some((TokenOrUnit::Group, lp_start, _)) => break (true, lp_start),
+							Some((TokenOrUnit::ListOpen, _, _)) | None => {
+								return Err(error((
+									ParseErrorKind::UnmatchedRightParenthesis,
+									start,
+									end,
+								)));
+							}
+							Some(item) => items.push_front(item),
+						}
+					};
+					let mut elems = classify(operators, items).map_err(error)?;
+					convert(&mut current_pred, &mut elems, &mut variables);
+					let (id, name_start) = if group {
+						if elems.is_empty() {
+							return Err(error((ParseErrorKind::MissingGoal, lp_start, end)));
+						}
+						(
+							reduce(operators, &mut current_pred, &elems, TERM_PRIORITY)
+								.map_err(error)?,
+							lp_start,
+						)
+					} else {
+						let (name, name_start) = match token_stack.pop() {
+							Some((
+								TokenOrUnit::Ident(name) | TokenOrUnit::Quoted(name),
+								name_start,
+								_,
+							)) => (name, name_start),
+							_ => unreachable!(),
+						};
+						let mut args = Vec::new();
+						for arg in elems
+							.split(|elem| matches!(elem, Elem::Infix(ident, _, _) if ident == ","))
+						{
+							if arg.is_empty() {
+								if elems.is_empty() {
 									break;
 								}
-								TokenOrUnit::Ident(string) => {
-									if string == "_" {
-										id_list.push_front(current_pred.push_node(
-											format!("_{}", suffix_alloc_id),
-											Vec::new(),
-										));
-										suffix_alloc_id += 1;
-									} else {
-										id_list
-											.push_front(current_pred.push_node(string, Vec::new()));
-									}
-								}
-								TokenOrUnit::Unit(id) => {
-									id_list.push_front(id);
-								}
-								_ => panic!("Invalid element during rightp collasping!"),
-							},
-							None => {
-								panic!("Unmatched rightp!");
+								return Err(error((ParseErrorKind::MissingOperand, lp_start, end)));
 							}
+							args.push(
+								reduce(operators, &mut current_pred, arg, ARGUMENT_PRIORITY)
+									.map_err(error)?,
+							);
 						}
+						// name() stands for the atom name
+						let term = if args.is_empty() {
+							Term::Atom(Symbol::intern(&name))
+						} else {
+							Term::Compound(Functor::new(Symbol::intern(&name), args.len()), args)
+						};
+						(current_pred.push(term), name_start)
+					};
+					plevel -= 1;
+					token_stack.push((TokenOrUnit::Unit(id), name_start, end));
+				}
+				TokenOrUnit::ListOpen | TokenOrUnit::Bar => {
+					if let TokenOrUnit::ListOpen = token {
+						plevel += 1;
 					}
+					token_stack.push((token, start, end));
 				}
-				other => {
-					token_stack.push(other);
+				TokenOrUnit::ListClose => {
+					let mut items = VecDeque::new();
+					let lb_start = loop {
+						match token_stack.pop() {
+							Some((TokenOrUnit::ListOpen, lb_start, _)) => break lb_start,
+							Some((TokenOrUnit::LeftParenthesis | TokenOrUnit::Group, _, _))
+							| None => {
+								return Err(error((
+									ParseErrorKind::UnmatchedRightBracket,
+									start,
+									end,
+								)));
+							}
+							Some(item) => items.push_front(item),
+						}
+					};
+					// the elements before the first |, the tail after it
+					let bar = items
+						.iter()
+						.position(|item| matches!(item.0, TokenOrUnit::Bar));
+					let tail = match bar {
+						None => None,
+						Some(bar) => {
+							let mut tail = items.split_off(bar);
+							let (_, bar_start, bar_end) = tail.pop_front().unwrap();
+							let elems = classify(operators, tail).map_err(error)?;
+							if elems.is_empty() {
+								return Err(error((
+									ParseErrorKind::MissingOperand,
+									bar_start,
+									bar_end,
+								)));
+							}
+							Some(elems)
+						}
+					};
+					let mut elems = classify(operators, items).map_err(error)?;
+					if elems.is_empty() && tail.is_some() {
+						return Err(error((ParseErrorKind::MissingOperand, lb_start, end)));
+					}
+					let tail = match tail {
+						None => None,
+						Some(mut tail) => {
+							convert(&mut current_pred, &mut tail, &mut variables);
+							Some(
+								reduce(operators, &mut current_pred, &tail, ARGUMENT_PRIORITY)
+									.map_err(error)?,
+							)
+						}
+					};
+					convert(&mut current_pred, &mut elems, &mut variables);
+					let mut items = Vec::new();
+					for item in
+						elems.split(|elem| matches!(elem, Elem::Infix(ident, _, _) if ident == ","))
+					{
+						if item.is_empty() {
+							if elems.is_empty() {
+								break;
+							}
+							return Err(error((ParseErrorKind::MissingOperand, lb_start, end)));
+						}
+						items.push(
+							reduce(operators, &mut current_pred, item, ARGUMENT_PRIORITY)
+								.map_err(error)?,
+						);
+					}
+					let mut id = match tail {
+						Some(tail) => tail,
+						None => current_pred.push(Term::Atom(Symbol::NIL)),
+					};
+					for item in items.into_iter().rev() {
+						id = current_pred.push(Term::Compound(Functor::CONS, vec![item, id]));
+					}
+					plevel -= 1;
+					token_stack.push((TokenOrUnit::Unit(id), lb_start, end));
+				}
+				TokenOrUnit::Quoted(_) | TokenOrUnit::Unit(_) | TokenOrUnit::Group => {
+					unreachable!()
 				}
 			}
 			remaining = new_remaining;
 		}
-		(result, suffix_alloc_id)
+		// only complete predicates may be left
+		for (token, start, end) in token_stack.iter() {
+			let kind = match token {
+				TokenOrUnit::LeftParenthesis | TokenOrUnit::Group => {
+					ParseErrorKind::UnmatchedLeftParenthesis
+				}
+				TokenOrUnit::ListOpen => ParseErrorKind::UnmatchedLeftBracket,
+				_ => continue,
+			};
+			return Err(error((kind, *start, *end)));
+		}
+		let goal_start = token_stack.first().map_or(0, |token| token.1);
+		let goal = finish_goal(
+			operators,
+			&mut token_stack,
+			&mut current_pred,
+			&mut variables,
+		)
+		.map_err(error)?;
+		push_goal(&mut result, &mut pred_num, goal, goal_start, string.len()).map_err(error)?;
+		if result.head.nodes.is_empty() && result.body.is_empty() {
+			return Err(error((ParseErrorKind::EmptyClause, 0, string.len())));
+		}
+		Ok((result, variables.next))
 	}
 
-	fn instantiate(&self, mut suffix_alloc_id: u32) -> (Clause, u32) {
-		let mut new_clause: Clause = Default::default();
-		let mut instmap: HashMap<String, String> = HashMap::new();
-		for each_pred in std::iter::once(&self.head).chain(self.body.iter()) {
-			let mut new_pred: Pred = Default::default();
-			for each_node in each_pred.nodes.iter() {
-				if each_node.get_type() == 0 {
-					match instmap.get(&each_node.ident) {
-						Some(name) => {
-							new_pred.push_node(name.to_string(), each_node.data.clone());
-						}
-						None => {
-							let name = format!("_{}", suffix_alloc_id);
-							instmap.insert(each_node.ident.clone(), name.clone());
-							new_pred.push_node(name, each_node.data.clone());
-						}
-					}
-					suffix_alloc_id += 1;
-				} else {
-					new_pred.nodes.push(each_node.clone());
+	// a copy with the variable ids moved up by id, and the id after them
+	fn instantiate(&self, id: u32) -> (Clause, u32) {
+		let mut next = id;
+		let mut rename = |pred: &Pred| {
+			let mut pred = pred.clone();
+			for node in pred.nodes.iter_mut() {
+				if let Term::Var(var) = node {
+					*var = Var {
+						id: var.id + id,
+						name: None,
+					};
+					next = next.max(var.id + 1);
 				}
 			}
-			if new_clause.head.nodes.is_empty() {
-				new_clause.head = new_pred;
-			} else {
-				new_clause.body.push(new_pred);
-			}
-		}
-		(new_clause, suffix_alloc_id)
+			pred
+		};
+		let new_clause = Clause {
+			head: rename(&self.head),
+			body: self.body.iter().map(&mut rename).collect(),
+		};
+		(new_clause, next)
 	}
 
-	pub fn match_target(&self, target: Pred, id: u32) -> Option<(VecDeque<Pred>, InstMap, u32)> {
-		let (insted, mut id) = self.instantiate(id);
-		match insted.head.match_target(target, id) {
-			None => None,
-			Some((instmap, new_id)) => {
-				id = new_id;
-				let result_vec = insted
-					.body
-					.iter()
-					.map(|x| x.instantiate(&instmap).unwrap()) // neq never appears here
-					.collect();
-				Some((result_vec, instmap, id))
-			}
+	// the body goals and bindings of resolving target, the variables of the
+	// clause are renamed from id on, which should be above those of target
+	pub fn match_target(
+		&self,
+		target: Pred,
+		id: u32,
+	) -> Result<Option<(VecDeque<Pred>, InstMap, u32)>, Pred> {
+		let mut bindings: Bindings = Default::default();
+		let target = bindings.add(&target);
+		Ok(self.resolve(&mut bindings, target, id)?.map(|(body, id)| {
+			let body = body.iter().map(|x| bindings.instantiate(x)).collect();
+			(body, bindings.instmap(), id)
+		}))
+	}
+
+	// the body goals of a renamed copy replacing the target node of bindings,
+	// the bindings of the head stay in bindings and are not substituted in the
+	// goals, None if the head does not unify, Err with the term a variable occurs
+	// in when the occurs check raises errors
+	pub fn resolve(
+		&self,
+		bindings: &mut Bindings,
+		target: usize,
+		id: u32,
+	) -> Result<Option<(VecDeque<Pred>, u32)>, Pred> {
+		let (insted, id) = self.instantiate(id);
+		let head = bindings.add(&insted.head);
+		if !bindings.unify(target, head)? {
+			return Ok(None);
 		}
+		Ok(Some((insted.body.into_iter().collect(), id)))
 	}
 }
 
 #[cfg(test)]
 mod test {
+	use std::cmp::Ordering;
+
 	use super::*;
+	use crate::operator::Fixity;
 
 	#[test]
 	fn clause_string_io() {
-		let (clause, _) = Clause::from_string("father(tom, bob)", 0);
+		let (clause, _) = Clause::from_string("father(tom, bob)", 0).unwrap();
 		assert_eq!(clause.to_string(), "father(tom, bob)");
-		let (clause, _) = Clause::from_string("greater(X, Y) :- greater(X, Z), greater(Z, Y)", 0);
+		let (clause, _) =
+			Clause::from_string("greater(X, Y) :- greater(X, Z), greater(Z, Y)", 0).unwrap();
 		assert_eq!(
 			clause.to_string(),
 			"greater(X, Y) :- greater(X, Z), greater(Z, Y)"
 		);
-		let (clause, _) = Clause::from_string("add(s(X), Y, s(Z)) :- add(X, Y, Z)", 0);
+		let (clause, _) = Clause::from_string("add(s(X), Y, s(Z)) :- add(X, Y, Z)", 0).unwrap();
 		assert_eq!(clause.to_string(), "add(s(X), Y, s(Z)) :- add(X, Y, Z)");
 	}
 
 	#[test]
 	fn clause_instantiate() {
-		let (clause, _) = Clause::from_string("greater(X, Y) :- greater(X, Z), greater(Z, Y)", 0);
-		// reversed order, instantiate is not recursive algorithm
+		let (clause, _) =
+			Clause::from_string("greater(X, Y) :- greater(X, Z), greater(Z, Y)", 0).unwrap();
+		let (insted, id) = clause.instantiate(5);
+		assert_eq!(
+			insted.to_string(),
+			"greater(_5, _6) :- greater(_5, _7), greater(_7, _6)"
+		);
+		assert_eq!(id, 8);
+	}
+
+	#[test]
+	fn clause_parse_error() {
+		let kind = |string| Clause::from_string(string, 0).unwrap_err().kind;
+		assert_eq!(kind("father(1tom, bob)"), ParseErrorKind::InvalidIdentifier);
+		assert_eq!(
+			kind("(father(tom, bob))"),
+			ParseErrorKind::InvalidPredicateName
+		);
+		assert_eq!(
+			kind("father(tom, bob))"),
+			ParseErrorKind::UnmatchedRightParenthesis
+		);
+		assert_eq!(
+			kind("father(tom, bob"),
+			ParseErrorKind::UnmatchedLeftParenthesis
+		);
+		assert_eq!(
+			kind("father(tom, bob) :- X"),
+			ParseErrorKind::DanglingIdentifier
+		);
+		assert_eq!(
+			kind("father(tom, bob) :- 1"),
+			ParseErrorKind::DanglingIdentifier
+		);
+		assert_eq!(kind(" :- "), ParseErrorKind::EmptyClause);
+		// a lone atom is a goal or a fact of arity 0
+		for string in [
+			"father(tom, bob) :- male",
+			"p(X) :- true",
+			"s",
+			"'s' :- t, !",
+		] {
+			assert!(Clause::from_string(string, 0).is_ok(), "{}", string);
+		}
+		let invalid = |string| {
+			let error = Clause::from_string(string, 0).unwrap_err();
+			(error.kind, error.span.start, error.span.end)
+		};
+		assert_eq!(invalid("été(x)"), (ParseErrorKind::InvalidCharacter, 0, 2));
+		assert_eq!(invalid("r({b})"), (ParseErrorKind::InvalidCharacter, 2, 3));
+		assert_eq!(
+			invalid("p(a) :- q(a) }"),
+			(ParseErrorKind::InvalidCharacter, 13, 14)
+		);
+		let error = Clause::from_string("father(tom,\n  9bob)", 0).unwrap_err();
+		assert_eq!(error.span.start, 14);
+		assert_eq!(error.span.end, 18);
+		assert_eq!(error.span.line, 2);
+		assert_eq!(error.span.column, 3);
+	}
+
+	#[test]
+	fn clause_negation() {
+		let (clause, _) =
+			Clause::from_string("bachelor(X) :- male(X), \\+ married(X, _)", 0).unwrap();
+		assert_eq!(clause.body[1].to_string(), "\\+ married(X, _1)");
+		let (clause, _) = Clause::from_string("a(Y) :- \\+ \\+ b(X), \\+(c)", 0).unwrap();
+		assert_eq!(clause.body[0].to_string(), "\\+ \\+ b(X)");
+		assert_eq!(clause.body[1].to_string(), "\\+ c");
+	}
+
+	#[test]
+	fn clause_control() {
+		for string in [
+			"a(X) :- (b(X) ; c(X)), d(X)",
+			"a(X) :- (b(X) -> c(X) ; d(X))",
+			"a(X) :- (b(X), c(X) *-> d(X) ; e(X), !)",
+			"a(X) :- ((b(X) ; c(X)) -> d(X) ; \\+ (e(X), f(X)))",
+		]
+		.iter()
+		{
+			let (clause, _) = Clause::from_string(string, 0).unwrap();
+			assert_eq!(clause.to_string(), *string);
+		}
+		let (clause, _) = Clause::from_string("a(X) :- (b(X), (c(X), d(X)))", 0).unwrap();
+		assert_eq!(clause.body.len(), 1);
+		assert_eq!(clause.body[0].get_name(), ",");
+		let kind = |string| Clause::from_string(string, 0).unwrap_err().kind;
+		assert_eq!(kind("a(X) :- (b(X) ; )"), ParseErrorKind::MissingGoal);
+		assert_eq!(kind("a(X) :- ( -> b(X))"), ParseErrorKind::MissingGoal);
+		assert_eq!(kind("(a(X) ; b(X))"), ParseErrorKind::InvalidPredicateName);
+	}
+
+	#[test]
+	fn clause_operators() {
+		for string in [
+			"a(X, Y) :- Y is X + 2 * 3 - 1",
+			"a(X, Y) :- Y is (X + 2) * (3 - 1)",
+			"a(X) :- X >= -1, X - (1 - 2) =\\= 0, \\+ X < 3",
+			"a(X) :- b((X, 1 mod 2)), (X =:= 1 ; X =< 2)",
+		]
+		.iter()
+		{
+			let (clause, _) = Clause::from_string(string, 0).unwrap();
+			assert_eq!(clause.to_string(), *string);
+		}
+		let (clause, _) = Clause::from_string("f(0042, -7, - 7, 1-2, 1 - -2, -(3))", 0).unwrap();
+		assert_eq!(clause.to_string(), "f(42, -7, -(7), 1 - 2, 1 - -2, -(3))");
+		let (big, _) =
+			Clause::from_string("f(99999999999999999999, -2r6, 4r2, 1r3 / 2)", 0).unwrap();
+		assert_eq!(big.to_string(), "f(99999999999999999999, -1r3, 2, 1r3 / 2)");
+		assert_eq!(
+			clause.head.args()[0].nodes[0].get_integer(),
+			Some(42.into())
+		);
+		let kind = |string| Clause::from_string(string, 0).unwrap_err().kind;
+		assert_eq!(kind("a(X) :- X is 1 +"), ParseErrorKind::MissingOperand);
+		assert_eq!(kind("a(X) :- X is 1 2"), ParseErrorKind::DanglingIdentifier);
+		assert_eq!(kind("a(X) :- b(1, , 2)"), ParseErrorKind::MissingOperand);
+		assert_eq!(kind("a(X) :- X is 1r0"), ParseErrorKind::InvalidIdentifier);
+		let (float, _) = Clause::from_string("f(3.140, -1.0e-9, 2.5E3, 2 ** 0.5)", 0).unwrap();
+		assert_eq!(float.to_string(), "f(3.14, -1.0e-9, 2500.0, 2 ** 0.5)");
+		// a float needs a fraction
+		assert_eq!(kind("a(X) :- X is 1e5"), ParseErrorKind::InvalidIdentifier);
+	}
+
+	#[test]
+	fn clause_comments() {
+		let (clause, _) = Clause::from_string(
+			"a(X, /* first */ Y) :- % the body\n b(X)/* c(, */, /* /* d */ e( */ c(Y)",
+			0,
+		)
+		.unwrap();
+		assert_eq!(clause.to_string(), "a(X, Y) :- b(X), c(Y)");
+		let (clause, _) = Clause::from_string("a(X) :- b(X)% c(X)", 0).unwrap();
+		assert_eq!(clause.to_string(), "a(X) :- b(X)");
+		let error = Clause::from_string("a(X) :- b(X) /* c /* d */", 0).unwrap_err();
+		assert_eq!(error.kind, ParseErrorKind::UnterminatedComment);
+		assert_eq!(error.span.start, 13);
+	}
+
+	#[test]
+	fn clause_quoted() {
+		let (clause, _) = Clause::from_string(
+			"city('New York', 'abc', 'it''s', '\\x41\\', '-'(1), 'to do'(X), ',', \"hi\", `a`) :- \
+			 b(X, 'line\\nbreak')",
+			0,
+		)
+		.unwrap();
+		assert_eq!(
+			clause.to_string(),
+			"city('New York', abc, 'it\\'s', 'A', -(1), 'to do'(X), ',', \
+			 [104, 105], [97]) :- b(X, 'line\\nbreak')"
+		);
+		let (clause, _) = Clause::from_string(&clause.to_string(), 0).unwrap();
+		assert!(matches!(clause.head.args()[0].root(), Term::Atom(_)));
+		assert!(matches!(clause.head.args()[7].root(), Term::Compound(_, _)));
+		// atoms are the same however they are written
+		assert_eq!(
+			clause.head.args()[0].root(),
+			&Term::Atom(Symbol::intern("New York"))
+		);
+		assert_eq!(clause.head.args()[1].get_name(), "abc");
+		assert_eq!(clause.head.args()[6].get_name(), ",");
+		let with = |double_quotes| {
+			let syntax = Syntax {
+				double_quotes,
+				..Default::default()
+			};
+			let (clause, _) = Clause::from_string_with("s(\"ab\", \"\")", 0, &syntax).unwrap();
+			clause.to_string()
+		};
+		assert_eq!(with(DoubleQuotes::Codes), "s([97, 98], [])");
+		assert_eq!(with(DoubleQuotes::Chars), "s([a, b], [])");
+		assert_eq!(with(DoubleQuotes::String), "s(\"ab\", \"\")");
+		let kind = |string| Clause::from_string(string, 0).unwrap_err().kind;
+		assert_eq!(kind("a('b, c)"), ParseErrorKind::UnterminatedQuoted);
+		assert_eq!(kind("a(\"b\\q\")"), ParseErrorKind::InvalidEscape);
+		assert_eq!(kind("a(b) :- 'c' 'd'"), ParseErrorKind::DanglingIdentifier);
+	}
+
+	#[test]
+	fn clause_lists() {
+		for string in [
+			"a([], [a], [a, b, c], [H|T], [a, b|T], [[1, 2], f(X)|[]])",
+			"a([X + 1, (b ; c)|T]) :- b([_0|_1], [(p, q)])",
+		]
+		.iter()
+		{
+			let (clause, _) = Clause::from_string(string, 0).unwrap();
+			assert_eq!(clause.to_string(), string.replace("|[]", ""));
+		}
+		let (clause, _) = Clause::from_string("a([ ], [a|[b]], '[|]'(c, []))", 0).unwrap();
+		assert_eq!(clause.to_string(), "a([], [a, b], [c])");
+		let items = clause.head.args()[1].list_items().unwrap();
+		assert_eq!(Pred::list(items), clause.head.args()[1]);
+		assert_eq!(clause.head.args()[0].list_items(), Some(Vec::new()));
+		let (clause, _) = Clause::from_string("a([_, _|_])", 0).unwrap();
+		assert_eq!(clause.to_string(), "a([_1, _2|_0])");
+		let kind = |string| Clause::from_string(string, 0).unwrap_err().kind;
+		assert_eq!(kind("a([b, c)"), ParseErrorKind::UnmatchedRightParenthesis);
+		assert_eq!(kind("a(b, c])"), ParseErrorKind::UnmatchedRightBracket);
+		assert_eq!(kind("a([b, c)]"), ParseErrorKind::UnmatchedRightParenthesis);
+		assert_eq!(kind("a(b) :- [c"), ParseErrorKind::UnmatchedLeftBracket);
+		assert_eq!(kind("a([b|])"), ParseErrorKind::MissingOperand);
+		assert_eq!(kind("a([|b])"), ParseErrorKind::MissingOperand);
+		assert_eq!(kind("a([b, , c])"), ParseErrorKind::MissingOperand);
+		assert_eq!(kind("a([b|c|d])"), ParseErrorKind::DanglingIdentifier);
+		assert_eq!(kind("a(b | c)"), ParseErrorKind::DanglingIdentifier);
+	}
+
+	#[test]
+	fn clause_operator_table() {
+		for string in [
+			"a(X, Y) :- X = f(Y), Y \\= g, X =.. [f|_0]",
+			"a(X) :- X = (b :- c, d), X = 2 ^ 3 ^ 4, X = 1 - 2 - 3",
+			"a(X) :- X = 1 - (2 - 3), X = m : n : o",
+		]
+		.iter()
+		{
+			let (clause, _) = Clause::from_string(string, 0).unwrap();
+			assert_eq!(clause.to_string(), *string);
+		}
+		let (clause, _) = Clause::from_string("a(X) :- X = 2 ^ 3 ^ 4, X = 1 - 2 - 3", 0).unwrap();
+		assert_eq!(clause.body[0].args()[1].get_name(), "^");
+		assert_eq!(clause.body[0].args()[1].args()[1].get_name(), "^");
+		assert_eq!(clause.body[1].args()[1].args()[0].get_name(), "-");
+		let mut syntax: Syntax = Default::default();
+		assert!(syntax.operators.add(700, Fixity::Xfx, "likes"));
+		assert!(syntax.operators.add(200, Fixity::Xf, "++"));
+		assert!(syntax.operators.add(0, Fixity::Xfx, "=.."));
+		let (clause, _) =
+			Clause::from_string_with("mary likes X :- X likes wine, N = 3 ++ + 1", 0, &syntax)
+				.unwrap();
+		assert_eq!(clause.head.to_string(), "likes(mary, X)");
+		assert_eq!(
+			clause.to_string_with(&syntax.operators),
+			"mary likes X :- X likes wine, N = 3++ + 1"
+		);
+		// prefix and postfix operators read back as the same term
+		assert!(syntax.operators.add(200, Fixity::Xf, "done"));
+		for (string, expected) in [
+			(
+				"f(-(a), -(-(a)), -(1), -(-), - (a, b))",
+				"f(-a, - -a, -(1), -(-), - (a, b))",
+			),
+			(
+				"f(-(a + b), -(a) + b, -(a ^ b), -(a) ^ b)",
+				"f(- (a + b), -a + b, -a ^ b, (-a) ^ b)",
+			),
+			(
+				"f(\\+(a = b), \\+(a) = b, \\(\\(1)))",
+				"f(\\+ a = b, (\\+ a) = b, \\ \\ 1)",
+			),
+			(
+				"f(++(++(a)), ++(-), done(3), ++(a) = b, -(done(1)))",
+				"f((a++)++, ++(-), 3 done, a++ = b, - 1 done)",
+			),
+		]
+		.iter()
+		{
+			let (clause, _) = Clause::from_string_with(string, 0, &syntax).unwrap();
+			let written = clause.to_string_with(&syntax.operators);
+			assert_eq!(written, *expected);
+			let (reread, _) = Clause::from_string_with(&written, 0, &syntax).unwrap();
+			assert_eq!(reread.head.standard_cmp(&clause.head), Ordering::Equal);
+		}
+		let kind = |string, syntax| {
+			Clause::from_string_with(string, 0, syntax)
+				.unwrap_err()
+				.kind
+		};
+		assert_eq!(
+			kind("a(X) :- X =.. Y", &syntax),
+			ParseErrorKind::DanglingIdentifier
+		);
+		assert_eq!(
+			kind("a() :- b = c = d", &syntax),
+			ParseErrorKind::PriorityClash
+		);
+		assert_eq!(
+			kind("a() :- b ** c ** d", &syntax),
+			ParseErrorKind::PriorityClash
+		);
+		assert_eq!(kind("a(b :- c)", &syntax), ParseErrorKind::PriorityClash);
+		let error = Clause::from_string("a() :- b = c = d", 0).unwrap_err();
+		assert_eq!((error.span.start, error.span.end), (13, 14));
+	}
+
+	#[test]
+	fn clause_cut() {
+		let (clause, _) = Clause::from_string("first(X) :- member(X, l), !, q(X)", 0).unwrap();
+		assert_eq!(clause.to_string(), "first(X) :- member(X, l), !, q(X)");
 		assert_eq!(
-			clause.instantiate(0).0.to_string(),
-			"greater(_1, _0) :- greater(_1, _2), greater(_2, _0)"
+			Clause::from_string("! :- a(X)", 0).unwrap_err().kind,
+			ParseErrorKind::InvalidPredicateName
 		);
 	}
 }
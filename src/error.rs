@@ -0,0 +1,196 @@
+use crate::pred::Pred;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+	// identifiers must start with a letter or _
+	InvalidIdentifier,
+	// ( not preceded by an identifier
+	InvalidPredicateName,
+	UnmatchedLeftParenthesis,
+	UnmatchedRightParenthesis,
+	UnmatchedLeftBracket,
+	UnmatchedRightBracket,
+	// identifier outside of any predicate, e.g. a missing ()
+	DanglingIdentifier,
+	// no predicate at all
+	EmptyClause,
+	// unknown or malformed `:-` directive
+	InvalidDirective,
+	// control construct with nothing on one side, e.g. (a ; )
+	MissingGoal,
+	// operator or argument with nothing on one side, e.g. X is 1 +
+	MissingOperand,
+	// operand binding looser than its operator allows, e.g. a = b = c
+	PriorityClash,
+	// text after the last clause without a terminating .
+	UnterminatedClause,
+	// /* without its */
+	UnterminatedComment,
+	// quoted atom or string without its closing quote
+	UnterminatedQuoted,
+	// unknown \ escape sequence in a quoted item
+	InvalidEscape,
+	// character no token starts with, e.g. a lone { or an unquoted é
+	InvalidCharacter,
+	// input the reader fails on, e.g. bytes that are not UTF-8
+	Unreadable,
+}
+
+impl std::fmt::Display for ParseErrorKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let string = match self {
+			ParseErrorKind::InvalidIdentifier => "invalid identifier",
+			ParseErrorKind::InvalidPredicateName => "invalid predicate name",
+			ParseErrorKind::UnmatchedLeftParenthesis => "unmatched left parenthesis",
+			ParseErrorKind::UnmatchedRightParenthesis => "unmatched right parenthesis",
+			ParseErrorKind::UnmatchedLeftBracket => "unmatched left bracket",
+			ParseErrorKind::UnmatchedRightBracket => "unmatched right bracket",
+			ParseErrorKind::DanglingIdentifier => "dangling identifier",
+			ParseErrorKind::EmptyClause => "empty clause",
+			ParseErrorKind::InvalidDirective => "invalid directive",
+			ParseErrorKind::MissingGoal => "missing goal",
+			ParseErrorKind::MissingOperand => "missing operand",
+			ParseErrorKind::PriorityClash => "operator priority clash",
+			ParseErrorKind::UnterminatedClause => "unterminated clause",
+			ParseErrorKind::UnterminatedComment => "unterminated comment",
+			ParseErrorKind::UnterminatedQuoted => "unterminated quoted",
+			ParseErrorKind::InvalidEscape => "invalid escape sequence",
+			ParseErrorKind::InvalidCharacter => "invalid character",
+			ParseErrorKind::Unreadable => "unreadable input",
+		};
+		write!(f, "{}", string)
+	}
+}
+
+// byte range, line and column are 1-based and point at start
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+	pub start: usize,
+	pub end: usize,
+	pub line: usize,
+	pub column: usize,
+}
+
+impl Span {
+	pub fn new(text: &str, start: usize, end: usize) -> Span {
+		let before = &text[..start];
+		Span {
+			start,
+			end,
+			line: before.matches('\n').count() + 1,
+			column: before.chars().rev().take_while(|&ch| ch != '\n').count() + 1,
+		}
+	}
+
+	// rebase a span of a clause onto the input, given the span of the clause
+	pub fn within(self, clause: Span) -> Span {
+		Span {
+			start: clause.start + self.start,
+			end: clause.start + self.end,
+			line: clause.line + self.line - 1,
+			column: if self.line == 1 {
+				clause.column + self.column - 1
+			} else {
+				self.column
+			},
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+	pub kind: ParseErrorKind,
+	pub clause: String,
+	pub span: Span,
+}
+
+impl std::fmt::Display for ParseError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{} at line {}, column {}: {}",
+			self.kind,
+			self.span.line,
+			self.span.column,
+			self.clause.trim()
+		)
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+// failure of Theory::add_reader
+#[derive(Debug)]
+pub enum ReadError {
+	Io(std::io::Error),
+	// the clauses that parse are added nonetheless
+	Parse(Vec<ParseError>),
+}
+
+impl std::fmt::Display for ReadError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ReadError::Io(error) => write!(f, "{}", error),
+			ReadError::Parse(errors) => {
+				for error in errors.iter() {
+					writeln!(f, "{}", error)?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+impl std::error::Error for ReadError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProveErrorKind {
+	// an operand of an arithmetic built-in is an unbound variable
+	Instantiation,
+	// neither a number nor an arithmetic functor
+	NotEvaluable,
+	ZeroDivisor,
+	// operand of an integer function such as // or mod is not an integer
+	NotInteger,
+	// the result is not a number, as in sqrt(-1)
+	Undefined,
+	// the result is too large for a float
+	FloatOverflow,
+	// the result of ** or << has more than arith::MAX_BITS bits
+	IntegerOverflow,
+	// unification would bind a variable to a term containing it
+	OccursCheck,
+}
+
+impl std::fmt::Display for ProveErrorKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let string = match self {
+			ProveErrorKind::Instantiation => "instantiation error",
+			ProveErrorKind::NotEvaluable => "not evaluable",
+			ProveErrorKind::ZeroDivisor => "division by zero",
+			ProveErrorKind::NotInteger => "integer expected",
+			ProveErrorKind::Undefined => "undefined",
+			ProveErrorKind::FloatOverflow => "float overflow",
+			ProveErrorKind::IntegerOverflow => "integer overflow",
+			ProveErrorKind::OccursCheck => "occurs check",
+		};
+		write!(f, "{}", string)
+	}
+}
+
+// raised by a built-in or by the occurs check, aborts the whole query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProveError {
+	pub kind: ProveErrorKind,
+	pub goal: Pred,
+	// the offending subterm of goal
+	pub culprit: Pred,
+}
+
+impl std::fmt::Display for ProveError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} in {}: {}", self.kind, self.goal, self.culprit)
+	}
+}
+
+impl std::error::Error for ProveError {}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use crate::pred::{Pred, Term};
+use crate::symbol::Functor;
+
+// principal functor of a compound, the constant itself otherwise
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+	Functor(Functor),
+	Atomic(Term),
+}
+
+// key of every argument, None for a variable
+fn argument_keys(pred: &Pred) -> Vec<Option<Key>> {
+	pred.root()
+		.args()
+		.iter()
+		.map(|id| match &pred.nodes[*id] {
+			Term::Var(_) => None,
+			Term::Compound(functor, _) => Some(Key::Functor(*functor)),
+			atomic => Some(Key::Atomic(atomic.clone())),
+		})
+		.collect()
+}
+
+#[derive(Clone, Debug, Default)]
+struct ArgIndex {
+	functors: HashMap<Key, Vec<usize>>,
+	// clauses with a variable at this position match any functor
+	variables: Vec<usize>,
+}
+
+// clause heads of one predicate indexed on each argument position
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ClauseIndex {
+	args: Vec<ArgIndex>,
+	len: usize,
+}
+
+impl ClauseIndex {
+	pub fn push(&mut self, head: &Pred) {
+		let id = self.len;
+		self.len += 1;
+		for (i, key) in argument_keys(head).into_iter().enumerate() {
+			if self.args.len() <= i {
+				self.args.push(Default::default());
+			}
+			match key {
+				None => self.args[i].variables.push(id),
+				Some(key) => self.args[i].functors.entry(key).or_default().push(id),
+			}
+		}
+	}
+
+	// ids of the clauses whose head may unify with target in ascending order,
+	// None if no argument of target is bound
+	pub fn candidates(&self, target: &Pred) -> Option<Vec<usize>> {
+		let mut result: Option<Vec<usize>> = None;
+		for (i, key) in argument_keys(target).into_iter().enumerate() {
+			let key = match key {
+				None => continue,
+				Some(key) => key,
+			};
+			let arg = match self.args.get(i) {
+				None => return Some(Vec::new()),
+				Some(arg) => arg,
+			};
+			let mut ids = arg.functors.get(&key).cloned().unwrap_or_default();
+			ids.extend(arg.variables.iter());
+			ids.sort_unstable();
+			result = Some(match result {
+				None => ids,
+				Some(prev) => prev
+					.into_iter()
+					.filter(|x| ids.binary_search(x).is_ok())
+					.collect(),
+			});
+		}
+		result
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::clause::Clause;
+
+	fn pred(string: &str) -> Pred {
+		Clause::from_string(string, 0).unwrap().0.head
+	}
+
+	#[test]
+	fn index_candidates() {
+		let mut index: ClauseIndex = Default::default();
+		for head in [
+			"number(d0, h)",
+			"number(d1, h)",
+			"number(d0, X)",
+			"number(X, s(Y))",
+			"number(d1, s(h))",
+		]
+		.iter()
+		{
+			index.push(&pred(head));
+		}
+		assert_eq!(index.candidates(&pred("number(X, Y)")), None);
+		assert_eq!(
+			index.candidates(&pred("number(d0, Y)")),
+			Some(vec![0, 2, 3])
+		);
+		assert_eq!(index.candidates(&pred("number(d1, h)")), Some(vec![1]));
+		assert_eq!(
+			index.candidates(&pred("number(X, s(a))")),
+			Some(vec![2, 3, 4])
+		);
+		assert_eq!(index.candidates(&pred("number(d2, s(h))")), Some(vec![3]));
+		assert_eq!(
+			index.candidates(&pred("number(d2, h, h)")),
+			Some(Vec::new())
+		);
+	}
+}
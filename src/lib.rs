@@ -1,6 +1,22 @@
 extern crate ntest;
+extern crate num_bigint;
+extern crate num_integer;
+extern crate num_rational;
+extern crate num_traits;
 extern crate plex;
 
-mod clause;
-mod pred;
+pub mod arith;
+pub mod bindings;
+pub mod clause;
+pub mod error;
+mod index;
+pub mod operator;
+pub mod pred;
+pub mod proof;
+mod quote;
+pub mod reader;
+pub mod search_tree;
+pub mod symbol;
+mod table;
 pub mod theory;
+pub mod tracer;
@@ -0,0 +1,53 @@
+% List predicates, loaded by :- use_module(library(lists)).
+% msort/2 and sort/2 are built-in.
+
+%! append(?List1, ?List2, ?List)
+%  List is List1 followed by List2.
+append([], L, L).
+append([H|T], L, [H|R]) :- append(T, L, R).
+
+%! member(?Elem, ?List)
+member(X, [X|_]).
+member(X, [_|T]) :- member(X, T).
+
+%! length(?List, ?N)
+%  Enumerates lists of increasing length when both are unbound.
+length(L, N) :- var(N), '$length'(L, 0, N).
+length(L, N) :- integer(N), N >= 0, '$length_down'(L, N).
+
+'$length'([], N, N).
+'$length'([_|T], N0, N) :- N1 is N0 + 1, '$length'(T, N1, N).
+
+'$length_down'([], 0).
+'$length_down'([_|T], N) :- N > 0, N1 is N - 1, '$length_down'(T, N1).
+
+%! reverse(?List, ?Reversed)
+reverse(L, R) :- '$reverse'(L, [], R).
+
+'$reverse'([], R, R).
+'$reverse'([H|T], A, R) :- '$reverse'(T, [H|A], R).
+
+%! nth0(?Index, ?List, ?Elem)
+%  Elem is at position Index of List, counting from 0.
+nth0(I, L, E) :- '$nth'(L, 0, I, E).
+
+%! nth1(?Index, ?List, ?Elem)
+%  Elem is at position Index of List, counting from 1.
+nth1(I, L, E) :- '$nth'(L, 1, I, E).
+
+'$nth'([H|_], I, I, H).
+'$nth'([_|T], B0, I, E) :-
+	(var(I) -> B0 >= 0 ; B0 < I),
+	B1 is B0 + 1,
+	'$nth'(T, B1, I, E).
+
+%! last(?List, ?Last)
+last([X], X).
+last([_|T], X) :- last(T, X).
+
+%! permutation(+List, ?Permutation)
+permutation([], []).
+permutation(L, [H|T]) :- '$select'(H, L, R), permutation(R, T).
+
+'$select'(X, [X|T], T).
+'$select'(X, [H|T], [H|R]) :- '$select'(X, T, R).
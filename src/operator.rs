@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// x: operand of lower priority, y: operand of lower or equal priority
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fixity {
+	Xfx,
+	Xfy,
+	Yfx,
+	Fy,
+	Fx,
+	Xf,
+	Yf,
+}
+
+impl Fixity {
+	// the type argument of op/3
+	pub fn from_name(name: &str) -> Option<Fixity> {
+		Some(match name {
+			"xfx" => Fixity::Xfx,
+			"xfy" => Fixity::Xfy,
+			"yfx" => Fixity::Yfx,
+			"fy" => Fixity::Fy,
+			"fx" => Fixity::Fx,
+			"xf" => Fixity::Xf,
+			"yf" => Fixity::Yf,
+			_ => return None,
+		})
+	}
+
+	// maximum priority of the left and right operands of an operator of priority
+	pub fn operand_priorities(self, priority: usize) -> (usize, usize) {
+		match self {
+			Fixity::Xfx => (priority - 1, priority - 1),
+			Fixity::Xfy => (priority - 1, priority),
+			Fixity::Yfx => (priority, priority - 1),
+			Fixity::Fy => (0, priority),
+			Fixity::Fx => (0, priority - 1),
+			Fixity::Xf => (priority - 1, 0),
+			Fixity::Yf => (priority, 0),
+		}
+	}
+}
+
+// priority of a term that is an argument of a compound term
+pub(crate) const ARGUMENT_PRIORITY: usize = 999;
+// priority of a whole term, a goal or a parenthesized term
+pub(crate) const TERM_PRIORITY: usize = 1200;
+
+// operators by name with their priority and fixity, an infix and a postfix
+// operator never share a name
+#[derive(Clone, Debug)]
+pub struct Operators {
+	infix: HashMap<String, (usize, Fixity)>,
+	prefix: HashMap<String, (usize, Fixity)>,
+	postfix: HashMap<String, (usize, Fixity)>,
+}
+
+// the ISO operators and the usual directive prefixes
+const STANDARD: [(usize, Fixity, &[&str]); 17] = [
+	(1200, Fixity::Xfx, &[":-", "-->"]),
+	(1200, Fixity::Fx, &[":-", "?-"]),
+	(
+		1150,
+		Fixity::Fx,
+		&[
+			"dynamic",
+			"discontiguous",
+			"initialization",
+			"multifile",
+			"table",
+		],
+	),
+	(1100, Fixity::Xfy, &[";"]),
+	(1050, Fixity::Xfy, &["->", "*->"]),
+	(1000, Fixity::Xfy, &[","]),
+	(900, Fixity::Fy, &["\\+"]),
+	(700, Fixity::Xfx, &["=", "\\=", "=..", "is"]),
+	(700, Fixity::Xfx, &["<", "=<", ">", ">=", "=:=", "=\\="]),
+	(700, Fixity::Xfx, &["==", "\\==", "@<", "@=<", "@>", "@>="]),
+	(600, Fixity::Xfy, &[":"]),
+	(500, Fixity::Yfx, &["+", "-", "/\\", "\\/", "xor"]),
+	(400, Fixity::Yfx, &["*", "/", "//", "mod", "rem", "div"]),
+	(400, Fixity::Yfx, &["<<", ">>"]),
+	(200, Fixity::Xfx, &["**"]),
+	(200, Fixity::Xfy, &["^"]),
+	(200, Fixity::Fy, &["-", "+", "\\"]),
+];
+
+impl Default for Operators {
+	fn default() -> Operators {
+		let mut operators = Operators {
+			infix: HashMap::new(),
+			prefix: HashMap::new(),
+			postfix: HashMap::new(),
+		};
+		for (priority, fixity, names) in STANDARD.iter() {
+			for name in names.iter() {
+				operators
+					.table(*fixity)
+					.insert(name.to_string(), (*priority, *fixity));
+			}
+		}
+		operators
+	}
+}
+
+impl Operators {
+	fn table(&mut self, fixity: Fixity) -> &mut HashMap<String, (usize, Fixity)> {
+		match fixity {
+			Fixity::Xfx | Fixity::Xfy | Fixity::Yfx => &mut self.infix,
+			Fixity::Fy | Fixity::Fx => &mut self.prefix,
+			Fixity::Xf | Fixity::Yf => &mut self.postfix,
+		}
+	}
+
+	pub fn infix(&self, name: &str) -> Option<(usize, Fixity)> {
+		self.infix.get(name).copied()
+	}
+
+	pub fn prefix(&self, name: &str) -> Option<(usize, Fixity)> {
+		self.prefix.get(name).copied()
+	}
+
+	pub fn postfix(&self, name: &str) -> Option<(usize, Fixity)> {
+		self.postfix.get(name).copied()
+	}
+
+	pub fn is_operator(&self, name: &str) -> bool {
+		self.infix.contains_key(name)
+			|| self.prefix.contains_key(name)
+			|| self.postfix.contains_key(name)
+	}
+
+	// op/3, priority 0 removes the operator, false if it may not be defined
+	pub fn add(&mut self, priority: usize, fixity: Fixity, name: &str) -> bool {
+		if priority > TERM_PRIORITY || matches!(name, "," | "|" | "[]" | "{}") {
+			return false;
+		}
+		let clash = match fixity {
+			Fixity::Xfx | Fixity::Xfy | Fixity::Yfx => self.postfix.contains_key(name),
+			Fixity::Xf | Fixity::Yf => self.infix.contains_key(name),
+			Fixity::Fy | Fixity::Fx => false,
+		};
+		if clash && priority > 0 {
+			return false;
+		}
+		let table = self.table(fixity);
+		if priority == 0 {
+			table.remove(name);
+		} else {
+			table.insert(name.to_string(), (priority, fixity));
+		}
+		true
+	}
+}
+
+// the table used where no theory is at hand, e.g. Display
+pub(crate) fn standard() -> &'static Operators {
+	static STANDARD_OPERATORS: OnceLock<Operators> = OnceLock::new();
+	STANDARD_OPERATORS.get_or_init(Default::default)
+}
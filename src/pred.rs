@@ -1,18 +1,36 @@
 #[allow(unused_imports)]
 use ntest::timeout;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use num_bigint::BigInt;
+
+use crate::arith::{self, Number};
+use crate::bindings::{Bindings, OccursCheck};
+use crate::operator::{self, Operators, ARGUMENT_PRIORITY};
+use crate::quote;
+use crate::symbol::{Functor, Symbol};
 
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct Pred {
-	pub nodes: Vec<PredNode>,
+	pub nodes: Vec<Term>,
 }
 
-pub type InstMap = HashMap<String, Pred>;
+pub type InstMap = HashMap<Var, Pred>;
+
+// arguments come before their compound in nodes except in a cyclic term, where
+// an argument may point back to an ancestor; while a term is built this stands
+// in for the ancestor at depth, which is not pushed yet
+pub(crate) fn back_reference(depth: usize) -> usize {
+	usize::MAX - depth
+}
 
 pub fn instmap_to_string(map: &InstMap) -> String {
 	let mut result = String::new();
 	for (key, value) in map.iter() {
-		result += key;
+		result += &key.to_string();
 		result += "->";
 		result += &value.to_string();
 		result += " ";
@@ -20,223 +38,241 @@ pub fn instmap_to_string(map: &InstMap) -> String {
 	result
 }
 
-fn instmap_compress(mut map: InstMap) -> InstMap {
-	if map.is_empty() {
-		return map;
+impl Pred {
+	pub fn root(&self) -> &Term {
+		self.nodes.last().unwrap()
 	}
-	let mut graph: HashMap<String, Vec<Pred>> = Default::default();
-	for (key, value) in map.into_iter() {
-		match graph.remove(&key) {
-			Some(mut vec) => {
-				vec.push(value.clone());
-				graph.insert(key.clone(), vec);
-			}
-			None => {
-				graph.insert(key.clone(), vec![value.clone()]);
-			}
-		}
-		if value.get_type() == 0 {
-			match graph.remove(&value.nodes[0].ident) {
-				Some(mut vec) => {
-					vec.push(Pred::vc_from_string(key));
-					graph.insert(value.nodes[0].ident.clone(), vec);
-				}
-				None => {
-					graph.insert(
-						value.nodes[0].ident.clone(),
-						vec![Pred::vc_from_string(key)],
-					);
-				}
-			}
-		}
+
+	pub fn get_name(&self) -> String {
+		self.root().name().into_owned()
 	}
 
-	map = Default::default();
+	pub fn is_var(&self) -> bool {
+		self.root().is_var()
+	}
 
-	let mut concomp_queue = Vec::new();
-	let mut concomp_collect: Vec<String> = Vec::new();
-	let mut non_variable: Option<Pred> = None;
-	loop {
-		match concomp_queue.pop() {
-			None => {
-				if concomp_collect.is_empty() {
-					let key = graph.keys().next().unwrap();
-					concomp_queue.push(key.clone());
-					continue;
-				}
-				if non_variable.is_none() {
-					non_variable = Some(Pred::vc_from_string(concomp_collect[0].clone()));
+	pub fn is_cyclic(&self) -> bool {
+		self.nodes
+			.iter()
+			.enumerate()
+			.any(|(id, node)| node.args().iter().any(|arg| *arg >= id))
+	}
+
+	fn clone_subtree(&self, target: &mut Pred, id: usize) -> usize {
+		self.copy_recurse(target, &InstMap::new(), &mut Vec::new(), id)
+	}
+
+	// ancestors are the ids being copied and whether a back reference points to them
+	fn copy_recurse(
+		&self,
+		target: &mut Pred,
+		instmap: &InstMap,
+		ancestors: &mut Vec<(usize, bool)>,
+		id: usize,
+	) -> usize {
+		match &self.nodes[id] {
+			Term::Compound(functor, args) => {
+				let start = target.nodes.len();
+				ancestors.push((id, false));
+				let mut new_args = Vec::with_capacity(args.len());
+				for arg in args.iter() {
+					let depth = if *arg >= id {
+						ancestors.iter().position(|(ancestor, _)| ancestor == arg)
+					} else {
+						None
+					};
+					new_args.push(match depth {
+						Some(depth) => {
+							ancestors[depth].1 = true;
+							back_reference(depth)
+						}
+						None => self.copy_recurse(target, instmap, ancestors, *arg),
+					});
 				}
-				for each_var in concomp_collect.into_iter() {
-					map.insert(each_var, non_variable.clone().unwrap());
+				let result = target.push(Term::Compound(*functor, new_args));
+				if ancestors.pop().unwrap().1 {
+					target.resolve_back_references(start, ancestors.len(), result);
 				}
-				concomp_collect = Vec::new();
-				non_variable = None;
-				match graph.keys().next() {
-					None => break,
-					Some(key) => concomp_queue.push(key.clone()),
-				};
+				result
 			}
-			Some(string) => {
-				for each_pred in match graph.remove(&string) {
-					Some(vec) => vec.into_iter(),
-					None => Vec::new().into_iter(),
-				} {
-					if each_pred.get_type() == 0 {
-						concomp_queue.push(each_pred.nodes[0].ident.clone());
-					} else {
-						non_variable = Some(each_pred);
+			// values are resolved already
+			Term::Var(var) => match instmap.get(var) {
+				None => target.push(self.nodes[id].clone()),
+				Some(pred) => pred.clone_subtree(target, pred.nodes.len() - 1),
+			},
+			term => target.push(term.clone()),
+		}
+	}
+
+	// the back references to the ancestor at depth in the nodes from start on point to id
+	pub(crate) fn resolve_back_references(&mut self, start: usize, depth: usize, id: usize) {
+		for node in self.nodes[start..].iter_mut() {
+			if let Term::Compound(_, args) = node {
+				for arg in args.iter_mut() {
+					if *arg == back_reference(depth) {
+						*arg = id;
 					}
 				}
-				concomp_collect.push(string);
 			}
 		}
 	}
-	map
-}
 
-fn instmap_merge(mut map1: InstMap, map2: InstMap, mut id: u32) -> Option<(InstMap, u32)> {
-	if map1.is_empty() {
-		return Some((map2, id));
+	pub(crate) fn subtree(&self, id: usize) -> Pred {
+		let mut result: Pred = Default::default();
+		self.clone_subtree(&mut result, id);
+		result
 	}
-	if map2.is_empty() {
-		return Some((map1, id));
+
+	pub fn list(items: Vec<Pred>) -> Pred {
+		items
+			.into_iter()
+			.rev()
+			.fold(Pred::leaf(Term::Atom(Symbol::NIL)), |tail, item| {
+				Pred::compound(Symbol::CONS, vec![item, tail])
+			})
 	}
-	//println!("[31mMERGE[0m");
-	let mut map_list = vec![map2];
-	let mut merge_queue = HashMap::new().into_iter();
-	loop {
-		//println!("l {:?}", merge_queue);
-		let (key, value) = match merge_queue.next() {
-			Some((key, value)) => (key, value),
-			None => {
-				if map_list.is_empty() {
-					break;
-				} else {
-					merge_queue = map_list.pop().unwrap().into_iter();
-					continue;
-				}
-			}
-		};
-		match map1.remove(&key) {
-			None => {
-				map1.insert(key.to_string(), value);
-			}
-			Some(value1) => {
-				map1.insert(key.to_string(), value.clone());
-				if value != value1 {
-					let new_map = match value1.match_target(value, id) {
-						None => return None,
-						Some((new_map, new_id)) => {
-							id = new_id;
-							new_map
-						}
-					};
-					//println!("[33mPUSHING:[0m {:?}", new_map);
-					map_list.push(new_map);
-				}
-			}
-		}
+
+	fn is_cons(term: &Term) -> bool {
+		matches!(term, Term::Compound(functor, _) if *functor == Functor::CONS)
 	}
-	map1 = instmap_compress(map1);
-	//println!("[32mmerged[0m{:?}", map1);
-	Some((map1, id))
-}
 
-impl Pred {
-	pub fn get_name(&self) -> String {
-		self.nodes.last().unwrap().ident.clone()
+	// elements of a proper list, None for a partial list, a cyclic list or another term
+	pub fn list_items(&self) -> Option<Vec<Pred>> {
+		let mut result = Vec::new();
+		let mut id = self.nodes.len() - 1;
+		while Pred::is_cons(&self.nodes[id]) {
+			let args = self.nodes[id].args();
+			result.push(self.subtree(args[0]));
+			if args[1] >= id {
+				return None;
+			}
+			id = args[1];
+		}
+		if self.nodes[id] == Term::Atom(Symbol::NIL) {
+			Some(result)
+		} else {
+			None
+		}
 	}
 
-	fn clone_subtree(&self, target: &mut Pred, id: usize) -> usize {
-		let mut id_list = Vec::new();
-		for child in self.nodes[id].data.iter() {
-			id_list.push(self.clone_subtree(target, *child));
+	// '[|]'(x, '[|]'(y, [])) for the atomic terms x and y
+	pub(crate) fn push_list(&mut self, items: Vec<Term>) -> usize {
+		let mut tail = self.push(Term::Atom(Symbol::NIL));
+		for item in items.into_iter().rev() {
+			let head = self.push(item);
+			tail = self.push(Term::Compound(Functor::CONS, vec![head, tail]));
 		}
-		target.push_node(self.nodes[id].ident.clone(), id_list)
+		tail
 	}
 
-	fn subtree(&self, id: usize) -> Pred {
+	pub fn compound(name: Symbol, args: Vec<Pred>) -> Pred {
 		let mut result: Pred = Default::default();
-		self.clone_subtree(&mut result, id);
+		let args: Vec<usize> = args
+			.iter()
+			.map(|arg| arg.clone_subtree(&mut result, arg.nodes.len() - 1))
+			.collect();
+		result.push(Term::Compound(Functor::new(name, args.len()), args));
 		result
 	}
 
-	pub fn match_target(&self, target: Pred, mut suffix_alloc_id: u32) -> Option<(InstMap, u32)> {
-		let mut map: InstMap = HashMap::new();
-		// recurive head matcher
-		let mnode = self.nodes.last().unwrap().clone();
-		let tnode = target.nodes.last().unwrap().clone();
-		// println!("map {:#?} to {:#?}", self.to_string(), target.to_string());
-		match (self.get_type(), target.get_type()) {
-			(1, 2) | (2, 1) => return None,
-			(2, 2) | (1, 1) => {
-				if mnode.ident != tnode.ident {
-					return None;
-				}
-				for (m_node, t_node) in mnode.data.iter().zip(tnode.data.iter()) {
-					let mchild = self.subtree(*m_node);
-					let tchild = target.subtree(*t_node);
-					match mchild.match_target(tchild, suffix_alloc_id) {
-						None => return None,
-						Some((new_map, new_id)) => {
-							suffix_alloc_id = new_id;
-							match instmap_merge(map, new_map, suffix_alloc_id) {
-								None => return None,
-								Some((new_map, new_id)) => {
-									map = new_map;
-									suffix_alloc_id = new_id;
-								}
-							}
-						}
-					}
-				}
-			}
-			(0, 0) => {
-				let mut new_map = HashMap::new();
-				new_map.insert(mnode.ident, target);
-				match instmap_merge(map, new_map, suffix_alloc_id) {
-					None => return None,
-					Some((new_map, new_id)) => {
-						map = new_map;
-						suffix_alloc_id = new_id;
-					}
-				}
-			}
-			(0, 1) | (1, 0) => {
-				let (vid, cpred) = if mnode.get_type() == 0 {
-					(mnode.ident, tnode)
-				} else {
-					(tnode.ident, mnode)
-				};
-				map.insert(vid, cpred.to_vc());
-			}
-			(0, 2) | (2, 0) => {
-				let (vid, pred) = if mnode.get_type() == 0 {
-					(mnode.ident, target)
-				} else {
-					(tnode.ident, self.clone())
-				};
-				map.insert(vid, pred);
-			}
-			_ => unreachable!(),
-		}
-		Some((map, suffix_alloc_id))
+	// a term of a single node, a variable or an atomic term
+	pub fn leaf(term: Term) -> Pred {
+		Pred { nodes: vec![term] }
+	}
+
+	pub fn match_target(&self, target: Pred) -> Result<Option<InstMap>, Pred> {
+		self.unify_with(&target, OccursCheck::False)
 	}
 
-	pub fn push_node(&mut self, ident: String, data: Vec<usize>) -> usize {
-		self.nodes.push(PredNode { ident, data });
+	// bindings making self and other equal, Err with the term a variable occurs in
+	// when the occurs check raises errors
+	pub fn unify_with(
+		&self,
+		other: &Pred,
+		occurs_check: OccursCheck,
+	) -> Result<Option<InstMap>, Pred> {
+		let mut bindings = Bindings::new(occurs_check);
+		let root = bindings.add(self);
+		let other = bindings.add(other);
+		Ok(if bindings.unify(root, other)? {
+			Some(bindings.instmap())
+		} else {
+			None
+		})
+	}
+
+	pub fn push(&mut self, term: Term) -> usize {
+		self.nodes.push(term);
 		self.nodes.len() - 1
 	}
 
-	pub fn to_string_recurse(&self, id: usize) -> String {
-		let mut result = self.nodes[id].ident.clone();
-		if self.nodes[id].data.is_empty() {
+	// operators of the table are written in operator form, the subterms a
+	// cyclic term points back to are named _S1, _S2, ... and defined after where
+	pub fn to_string_with(&self, operators: &Operators) -> String {
+		if self.nodes.is_empty() {
+			return "Empty".to_string();
+		}
+		let mut cycles = Cycles::new(self);
+		let mut result = self.operand_to_string(
+			operators,
+			&mut cycles,
+			self.nodes.len() - 1,
+			ARGUMENT_PRIORITY,
+		);
+		let mut definitions = Vec::new();
+		while let Some(&id) = cycles.named.get(definitions.len()) {
+			let definition = self.node_to_string(operators, &mut cycles, id, ARGUMENT_PRIORITY);
+			definitions.push(format!("_S{} = {}", definitions.len() + 1, definition));
+		}
+		if !definitions.is_empty() {
+			result += " where ";
+			result += &definitions.join(", ");
+		}
+		result
+	}
+
+	// parenthesized if the operator binds looser than max
+	fn operand_to_string(
+		&self,
+		operators: &Operators,
+		cycles: &mut Cycles,
+		id: usize,
+		max: usize,
+	) -> String {
+		match cycles.name(id) {
+			Some(name) => name,
+			None => self.node_to_string(operators, cycles, id, max),
+		}
+	}
+
+	fn node_to_string(
+		&self,
+		operators: &Operators,
+		cycles: &mut Cycles,
+		id: usize,
+		max: usize,
+	) -> String {
+		match self.operator_priority(operators, id) {
+			Some(priority) if priority <= max => {
+				return self.operator_to_string(operators, cycles, id)
+			}
+			Some(_) => return format!("({})", self.operator_to_string(operators, cycles, id)),
+			None => {}
+		}
+		if Pred::is_cons(&self.nodes[id]) {
+			return self.list_to_string(operators, cycles, id);
+		}
+		let mut result = match &self.nodes[id] {
+			Term::Atom(name) => name.to_string(),
+			Term::Compound(functor, _) => quote::functor_ident(functor.name().name()),
+			term => term.name().into_owned(),
+		};
+		if self.nodes[id].args().is_empty() {
 			return result;
 		}
 		result += "(";
-		for each_node in self.nodes[id].data.iter() {
-			result += &self.to_string_recurse(*each_node);
+		for each_node in self.nodes[id].args().iter() {
+			result += &self.operand_to_string(operators, cycles, *each_node, ARGUMENT_PRIORITY);
 			result += ", ";
 		}
 		result.pop();
@@ -245,128 +281,390 @@ impl Pred {
 		result
 	}
 
-	pub fn vc_from_string(string: String) -> Pred {
-		Pred {
-			nodes: vec![PredNode {
-				ident: string,
-				data: Vec::new(),
-			}],
+	// [a, b], [a|T] for a partial list
+	fn list_to_string(&self, operators: &Operators, cycles: &mut Cycles, mut id: usize) -> String {
+		let mut items = Vec::new();
+		loop {
+			let args = self.nodes[id].args();
+			items.push(self.operand_to_string(operators, cycles, args[0], ARGUMENT_PRIORITY));
+			id = args[1];
+			if !Pred::is_cons(&self.nodes[id]) || cycles.contains(id) {
+				break;
+			}
 		}
+		let tail = if self.nodes[id] == Term::Atom(Symbol::NIL) {
+			String::new()
+		} else {
+			format!(
+				"|{}",
+				self.operand_to_string(operators, cycles, id, ARGUMENT_PRIORITY)
+			)
+		};
+		format!("[{}{}]", items.join(", "), tail)
 	}
 
-	fn instantiate_recurse(&self, target: &mut Pred, instmap: &InstMap, id: usize) -> usize {
-		//println!("{} {} {:#?}", self.to_string(), target.to_string(), instmap);
-		let ident = self.nodes[id].ident.clone();
-		if self.nodes[id].get_type() == 2 {
-			let data_vec: Vec<usize> = self.nodes[id]
-				.data
-				.iter()
-				.map(|x| self.instantiate_recurse(target, instmap, *x))
-				.collect();
-			target.push_node(ident, data_vec)
-		} else {
-			// No need to check const
-			match instmap.get(&ident) {
-				None => target.push_node(ident, Vec::new()),
-				Some(pred) => {
-					// variable may cause infinite recurse
-					if pred.get_type() != 2 {
-						return target.push_node(pred.nodes[0].ident.clone(), Vec::new());
-					}
-					// tricky!
-					pred.instantiate_recurse(target, instmap, pred.nodes.len() - 1)
-				}
+	// priority of the operator a compound is written with, None for the
+	// canonical form
+	fn operator_priority(&self, operators: &Operators, id: usize) -> Option<usize> {
+		let (name, args) = match &self.nodes[id] {
+			Term::Compound(functor, args) => (functor.name().name(), args),
+			_ => return None,
+		};
+		let operator = match args.len() {
+			2 => operators.infix(name),
+			// - 1 is -(1) and not the number -1, - - is -(-)
+			1 if matches!(name, "-" | "+") && matches!(self.nodes[args[0]], Term::Number(_)) => {
+				None
 			}
+			1 if matches!(&self.nodes[args[0]], Term::Atom(atom) if operators.is_operator(atom.name())) => {
+				None
+			}
+			1 => operators.prefix(name).or_else(|| operators.postfix(name)),
+			_ => None,
+		};
+		operator.map(|(priority, _)| priority)
+	}
+
+	fn operator_to_string(&self, operators: &Operators, cycles: &mut Cycles, id: usize) -> String {
+		let name = self.nodes[id].name();
+		let written = quote::atom_ident(&name);
+		let args = self.nodes[id].args();
+		if args.len() == 2 {
+			let (priority, fixity) = operators.infix(&name).unwrap();
+			let (left, right) = fixity.operand_priorities(priority);
+			let separator = if name == "," {
+				", ".to_string()
+			} else {
+				format!(" {} ", written)
+			};
+			return self.operand_to_string(operators, cycles, args[0], left)
+				+ &separator + &self.operand_to_string(operators, cycles, args[1], right);
+		}
+		if let Some((priority, fixity)) = operators.prefix(&name) {
+			let (_, right) = fixity.operand_priorities(priority);
+			let operand = self.operand_to_string(operators, cycles, args[0], right);
+			// -a, but - -a, - (a, b), - 1 done and \+ a
+			let glued = matches!(&*name, "-" | "+" | "\\")
+				&& !operand.starts_with(|ch: char| {
+					quote::is_symbol_char(ch) || ch == '(' || ch.is_ascii_digit()
+				});
+			return if glued {
+				format!("{}{}", written, operand)
+			} else {
+				format!("{} {}", written, operand)
+			};
+		}
+		let (priority, fixity) = operators.postfix(&name).unwrap();
+		let (left, _) = fixity.operand_priorities(priority);
+		let operand = self.operand_to_string(operators, cycles, args[0], left);
+		// 3++, but a - ++ and 3 done
+		let glued =
+			written.starts_with(quote::is_symbol_char) && !operand.ends_with(quote::is_symbol_char);
+		if glued {
+			format!("{}{}", operand, written)
+		} else {
+			format!("{} {}", operand, written)
 		}
 	}
 
 	// also do Neq check
 	pub fn instantiate(&self, instmap: &InstMap) -> Option<Pred> {
 		let mut result: Pred = Default::default();
-		self.instantiate_recurse(&mut result, instmap, self.nodes.len() - 1);
-		if result.nodes.last().unwrap().ident == "Neq" && result.nodes[0] == result.nodes[1] {
+		self.copy_recurse(&mut result, instmap, &mut Vec::new(), self.nodes.len() - 1);
+		if result.violates_neq() {
 			return None;
 		}
 		Some(result)
 	}
 
-	pub fn get_type(&self) -> i32 {
-		self.nodes.last().unwrap().get_type()
+	// a Neq whose sides became the same
+	pub fn violates_neq(&self) -> bool {
+		matches!(self.root(), Term::Compound(Functor::NEQ, _)) && self.nodes[0] == self.nodes[1]
+	}
+
+	pub fn args(&self) -> Vec<Pred> {
+		self.root()
+			.args()
+			.iter()
+			.map(|id| self.subtree(*id))
+			.collect()
+	}
+
+	pub fn is_ground(&self) -> bool {
+		self.nodes.iter().all(|node| !node.is_var())
+	}
+
+	// variables renumbered from 0 in order of first appearance and left
+	// unnamed, equal for variants
+	pub fn canonical(&self) -> Pred {
+		let variables = self.variables();
+		let mut result = self.clone();
+		for node in result.nodes.iter_mut() {
+			if let Term::Var(var) = node {
+				let id = variables.iter().position(|x| x == var).unwrap();
+				*var = Var {
+					id: id as u32,
+					name: None,
+				};
+			}
+		}
+		result
+	}
+
+	// variables in order of first appearance
+	pub fn variables(&self) -> Vec<Var> {
+		let mut result: Vec<Var> = Vec::new();
+		for node in self.nodes.iter() {
+			if let Term::Var(var) = node {
+				if !result.contains(var) {
+					result.push(*var);
+				}
+			}
+		}
+		result
+	}
+
+	// standard order of terms: variables, numbers, atoms, strings, then compounds
+	// by arity, name and arguments from left to right
+	pub fn standard_cmp(&self, other: &Pred) -> Ordering {
+		self.standard_cmp_recurse(
+			self.nodes.len() - 1,
+			other,
+			other.nodes.len() - 1,
+			&mut HashSet::new(),
+		)
+	}
+
+	// a pair of cyclic subterms met again compares equal, seen holds the pairs
+	// reached by a back reference
+	fn standard_cmp_recurse(
+		&self,
+		id: usize,
+		other: &Pred,
+		other_id: usize,
+		seen: &mut HashSet<(usize, usize)>,
+	) -> Ordering {
+		let rank = |term: &Term| match term {
+			Term::Var(_) => 0,
+			Term::Number(_) => 1,
+			Term::Atom(_) => 2,
+			Term::Str(_) => 3,
+			Term::Compound(_, _) => 4,
+		};
+		let (a, b) = (&self.nodes[id], &other.nodes[other_id]);
+		match (a, b) {
+			(Term::Number(a), Term::Number(b)) => a.standard_cmp(b),
+			(Term::Var(a), Term::Var(b)) => a.id.cmp(&b.id),
+			(Term::Atom(a), Term::Atom(b)) => a.name().cmp(b.name()),
+			(Term::Str(a), Term::Str(b)) => a.cmp(b),
+			(Term::Compound(a_functor, a_args), Term::Compound(b_functor, b_args)) => a_args
+				.len()
+				.cmp(&b_args.len())
+				.then_with(|| {
+					let (a_name, b_name) = (a_functor.name().name(), b_functor.name().name());
+					a_name.cmp(b_name)
+				})
+				.then_with(|| {
+					for (x, y) in a_args.iter().zip(b_args.iter()) {
+						if (*x >= id || *y >= other_id) && !seen.insert((*x, *y)) {
+							continue;
+						}
+						let ordering = self.standard_cmp_recurse(*x, other, *y, seen);
+						if ordering != Ordering::Equal {
+							return ordering;
+						}
+					}
+					Ordering::Equal
+				}),
+			_ => rank(a).cmp(&rank(b)),
+		}
+	}
+}
+
+// the nodes of a cyclic term printed by name, named in order of appearance
+struct Cycles {
+	targets: Vec<usize>,
+	named: Vec<usize>,
+}
+
+impl Cycles {
+	fn new(pred: &Pred) -> Cycles {
+		let mut targets = Vec::new();
+		for (id, node) in pred.nodes.iter().enumerate() {
+			for arg in node.args().iter() {
+				if *arg >= id && !targets.contains(arg) {
+					targets.push(*arg);
+				}
+			}
+		}
+		Cycles {
+			targets,
+			named: Vec::new(),
+		}
+	}
+
+	fn contains(&self, id: usize) -> bool {
+		self.targets.contains(&id)
+	}
+
+	fn name(&mut self, id: usize) -> Option<String> {
+		if !self.contains(id) {
+			return None;
+		}
+		let position = match self.named.iter().position(|x| *x == id) {
+			Some(position) => position,
+			None => {
+				self.named.push(id);
+				self.named.len() - 1
+			}
+		};
+		Some(format!("_S{}", position + 1))
 	}
 }
 
 impl std::fmt::Display for Pred {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		if self.nodes.is_empty() {
-			write!(f, "Empty")
-		} else {
-			write!(f, "{}", self.to_string_recurse(self.nodes.len() - 1))
+		write!(f, "{}", self.to_string_with(operator::standard()))
+	}
+}
+
+// a variable is told apart by its id, the name it was read with is only kept
+// to write it, renamed copies have none and are written _id
+#[derive(Clone, Copy, Debug)]
+pub struct Var {
+	pub id: u32,
+	pub name: Option<Symbol>,
+}
+
+impl PartialEq for Var {
+	fn eq(&self, other: &Var) -> bool {
+		self.id == other.id
+	}
+}
+
+impl Eq for Var {}
+
+impl Hash for Var {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.id.hash(state)
+	}
+}
+
+impl std::fmt::Display for Var {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.name {
+			Some(name) => write!(f, "{}", name.name()),
+			None => write!(f, "_{}", self.id),
 		}
 	}
 }
 
+// a node of a term, the arguments of a compound are ids of nodes before it
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct PredNode {
-	pub ident: String,
-	pub data: Vec<usize>,
+pub enum Term {
+	Var(Var),
+	Atom(Symbol),
+	Number(Number),
+	// text of a string object
+	Str(String),
+	Compound(Functor, Vec<usize>),
 }
 
-impl PredNode {
-	// 0: v, 1: c, 2: r
-	pub fn get_type(&self) -> i32 {
-		let vorc = match self.ident.chars().next().unwrap() {
-			'A'..='Z' => true,
-			'a'..='z' => false,
-			'_' => return 0,
-			_ => panic!("Unknown char!"),
-		};
-		if !self.data.is_empty() {
-			return 2;
+impl Term {
+	// name of a variable, text of an atom or functor name, the written form of a
+	// number or string
+	pub fn name(&self) -> Cow<'_, str> {
+		match self {
+			Term::Var(var) => match var.name {
+				Some(name) => Cow::Borrowed(name.name()),
+				None => Cow::Owned(var.to_string()),
+			},
+			Term::Atom(name) => Cow::Borrowed(name.name()),
+			Term::Compound(functor, _) => Cow::Borrowed(functor.name().name()),
+			Term::Number(value) => Cow::Owned(arith::number_to_string(value)),
+			Term::Str(text) => Cow::Owned(quote::string_ident(text)),
 		}
-		if vorc {
-			0
-		} else {
-			1
+	}
+
+	// name of an atom or a compound
+	pub fn symbol(&self) -> Option<Symbol> {
+		match self {
+			Term::Atom(name) => Some(*name),
+			Term::Compound(functor, _) => Some(functor.name()),
+			_ => None,
+		}
+	}
+
+	// name and arity of an atom or a compound
+	pub fn functor(&self) -> Option<Functor> {
+		match self {
+			Term::Atom(name) => Some(Functor::new(*name, 0)),
+			Term::Compound(functor, _) => Some(*functor),
+			_ => None,
+		}
+	}
+
+	pub fn args(&self) -> &[usize] {
+		match self {
+			Term::Compound(_, args) => args,
+			_ => &[],
 		}
 	}
 
-	fn to_vc(&self) -> Pred {
-		let mut node = self.clone();
-		node.data.drain(..);
-		Pred { nodes: vec![node] }
+	pub fn arity(&self) -> usize {
+		self.args().len()
+	}
+
+	pub fn is_var(&self) -> bool {
+		matches!(self, Term::Var(_))
+	}
+
+	pub fn get_var(&self) -> Option<Var> {
+		match self {
+			Term::Var(var) => Some(*var),
+			_ => None,
+		}
+	}
+
+	pub fn get_number(&self) -> Option<Number> {
+		match self {
+			Term::Number(value) => Some(value.clone()),
+			_ => None,
+		}
+	}
+
+	pub fn get_integer(&self) -> Option<BigInt> {
+		match self.get_number()? {
+			Number::Rational(value) if value.is_integer() => Some(value.to_integer()),
+			_ => None,
+		}
 	}
 }
 
 #[cfg(test)]
+#[allow(clippy::unnecessary_to_owned)]
 mod test {
 	use super::*;
 	use crate::clause::Clause;
+
+	// the value of the variable named name
+	fn value<'a>(map: &'a InstMap, name: &str) -> &'a Pred {
+		map.iter()
+			.find(|(var, _)| var.to_string() == name)
+			.unwrap()
+			.1
+	}
+
 	#[test]
 	#[timeout(1000)]
 	fn pred_match_vc() {
 		// construct a dummy clause to extract predicates
-		let (clause, _) = Clause::from_string("greater(X, Y) :- greater(x, y).", 0);
-		match clause.head.match_target(clause.body[0].clone(), 0) {
+		let (clause, _) = Clause::from_string("greater(X, Y) :- greater(x, y).", 0).unwrap();
+		match clause.head.match_target(clause.body[0].clone()).unwrap() {
 			None => panic!("VV match failed"),
-			Some((map, _)) => {
-				assert_eq!(
-					map.get(&"X".to_string())
-						.unwrap()
-						.nodes
-						.last()
-						.unwrap()
-						.ident,
-					"x"
-				);
-				assert_eq!(
-					map.get(&"Y".to_string())
-						.unwrap()
-						.nodes
-						.last()
-						.unwrap()
-						.ident,
-					"y"
-				);
+			Some(map) => {
+				assert_eq!(value(&map, "X").get_name(), "x");
+				assert_eq!(value(&map, "Y").get_name(), "y");
 			}
 		}
 	}
@@ -375,30 +673,19 @@ mod test {
 	#[timeout(1000)]
 	fn pred_match_vvvc() {
 		// construct a dummy clause to extract predicates
-		let (clause, _) = Clause::from_string("greater(X, X, b) :- greater(Y, a, Y).", 0);
-		assert!(clause
-			.head
-			.match_target(clause.body[0].clone(), 0)
-			.is_none());
+		let (clause, _) = Clause::from_string("greater(X, X, b) :- greater(Y, a, Y).", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
 	}
 
 	#[test]
 	#[timeout(1000)]
 	fn pred_match_vvvc2() {
 		// construct a dummy clause to extract predicates
-		let (clause, _) = Clause::from_string("greater(X, X, b) :- greater(Y, A, Y).", 0);
-		match clause.head.match_target(clause.body[0].clone(), 0) {
+		let (clause, _) = Clause::from_string("greater(X, X, b) :- greater(Y, A, Y).", 0).unwrap();
+		match clause.head.match_target(clause.body[0].clone()).unwrap() {
 			None => panic!("VV match failed"),
-			Some((map, _)) => {
-				assert_eq!(
-					map.get(&"A".to_string())
-						.unwrap()
-						.nodes
-						.last()
-						.unwrap()
-						.ident,
-					"b"
-				);
+			Some(map) => {
+				assert_eq!(value(&map, "A").get_name(), "b");
 			}
 		}
 	}
@@ -407,41 +694,27 @@ mod test {
 	#[timeout(1000)]
 	fn pred_match_vp_recurse_fail() {
 		// construct a dummy clause to extract predicates
-		let (clause, _) = Clause::from_string("greater(X, X) :- greater(f(x), f(y)).", 0);
-		assert!(clause
-			.head
-			.match_target(clause.body[0].clone(), 0)
-			.is_none());
+		let (clause, _) = Clause::from_string("greater(X, X) :- greater(f(x), f(y)).", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
 	}
 
 	#[test]
 	#[timeout(1000)]
 	fn pred_match_vp_pname_fail() {
 		// construct a dummy clause to extract predicates
-		let (clause, _) = Clause::from_string("greater(X, X) :- greater(f(x), g(x)).", 0);
-		assert!(clause
-			.head
-			.match_target(clause.body[0].clone(), 0)
-			.is_none());
+		let (clause, _) = Clause::from_string("greater(X, X) :- greater(f(x), g(x)).", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
 	}
 
 	#[test]
 	#[timeout(1000)]
 	fn pred_match_pp() {
 		// construct a dummy clause to extract predicates
-		let (clause, _) = Clause::from_string("greater(f(X)) :- greater(f(x)).", 0);
-		match clause.head.match_target(clause.body[0].clone(), 0) {
+		let (clause, _) = Clause::from_string("greater(f(X)) :- greater(f(x)).", 0).unwrap();
+		match clause.head.match_target(clause.body[0].clone()).unwrap() {
 			None => panic!("PP match failed"),
-			Some((map, _)) => {
-				assert_eq!(
-					map.get(&"X".to_string())
-						.unwrap()
-						.nodes
-						.last()
-						.unwrap()
-						.ident,
-					"x"
-				);
+			Some(map) => {
+				assert_eq!(value(&map, "X").get_name(), "x");
 			}
 		}
 	}
@@ -450,11 +723,8 @@ mod test {
 	#[timeout(1000)]
 	fn pred_match_pp_fail() {
 		// construct a dummy clause to extract predicates
-		let (clause, _) = Clause::from_string("greater(f(g(X))) :- greater(f(x)).", 0);
-		assert!(clause
-			.head
-			.match_target(clause.body[0].clone(), 0)
-			.is_none());
+		let (clause, _) = Clause::from_string("greater(f(g(X))) :- greater(f(x)).", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
 	}
 
 	#[test]
@@ -462,19 +732,11 @@ mod test {
 	fn pred_match_pp_recurse_complex() {
 		// construct a dummy clause to extract predicates
 		let (clause, _) =
-			Clause::from_string("greater(X, f(X, g(X))) :- greater(A, f(A, g(a))).", 0);
-		match clause.head.match_target(clause.body[0].clone(), 0) {
+			Clause::from_string("greater(X, f(X, g(X))) :- greater(A, f(A, g(a))).", 0).unwrap();
+		match clause.head.match_target(clause.body[0].clone()).unwrap() {
 			None => panic!("PP match failed"),
-			Some((map, _)) => {
-				assert_eq!(
-					map.get(&"X".to_string())
-						.unwrap()
-						.nodes
-						.last()
-						.unwrap()
-						.ident,
-					"a"
-				);
+			Some(map) => {
+				assert_eq!(value(&map, "X").get_name(), "a");
 			}
 		}
 	}
@@ -483,23 +745,21 @@ mod test {
 	#[timeout(1000)]
 	fn pred_match_pp_nested_instantiate() {
 		let (clause, _) =
-			Clause::from_string("greater(X, f(X, g(X))) :- greater(b, f(A, g(a))).", 0);
-		assert!(clause
-			.head
-			.match_target(clause.body[0].clone(), 0)
-			.is_none());
+			Clause::from_string("greater(X, f(X, g(X))) :- greater(b, f(A, g(a))).", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
 	}
 
 	#[test]
 	#[timeout(1000)]
 	fn pred_match_infinite_nest() {
-		let (clause, _) = Clause::from_string("greater(X, f(X)) :- greater(Y, Y).", 0);
-		match clause.head.match_target(clause.body[0].clone(), 0) {
+		let (clause, _) = Clause::from_string("greater(X, f(X)) :- greater(Y, Y).", 0).unwrap();
+		match clause.head.match_target(clause.body[0].clone()).unwrap() {
 			None => panic!("PP match failed"),
-			Some((map, _)) => {
-				let pred = map.get(&"Y".to_string()).unwrap();
-				assert_eq!(pred.nodes[0].ident, "X");
-				assert_eq!(pred.nodes[1].ident, "f");
+			Some(map) => {
+				let pred = value(&map, "Y");
+				assert!(pred.is_cyclic());
+				assert_eq!(pred.to_string(), "_S1 where _S1 = f(_S1)");
+				assert_eq!(value(&map, "X"), pred);
 			}
 		}
 	}
@@ -507,13 +767,16 @@ mod test {
 	#[test]
 	#[timeout(1000)]
 	fn pred_match_infinite_nest_2() {
-		let (clause, _) = Clause::from_string("greater(X, f(X), f(f(X))) :- greater(Y, Y, Y).", 0);
-		match clause.head.match_target(clause.body[0].clone(), 0) {
+		let (clause, _) =
+			Clause::from_string("greater(X, f(X), f(f(X))) :- greater(Y, Y, Y).", 0).unwrap();
+		match clause.head.match_target(clause.body[0].clone()).unwrap() {
 			None => panic!("PP match failed"),
-			Some((map, _)) => {
-				let pred = map.get(&"X".to_string()).unwrap();
-				assert_eq!(pred.nodes[0].ident, "X");
-				assert_eq!(pred.nodes[1].ident, "f");
+			Some(map) => {
+				let pred = value(&map, "X");
+				assert_eq!(pred.to_string(), "_S1 where _S1 = f(_S1)");
+				// f(f(X)) and f(X) are the same rational tree
+				assert_eq!(pred.args()[0].to_string(), "_S1 where _S1 = f(_S1)");
+				assert_eq!(pred.standard_cmp(&pred.args()[0]), Ordering::Equal);
 			}
 		}
 	}
@@ -521,77 +784,129 @@ mod test {
 	#[test]
 	#[timeout(1000)]
 	fn pred_match_infinite_nest_3() {
-		let (clause, _) = Clause::from_string("greater(X, f(X), f(f(a))) :- greater(Y, Y, Y).", 0);
-		assert!(clause
-			.head
-			.match_target(clause.body[0].clone(), 0)
-			.is_none());
+		let (clause, _) =
+			Clause::from_string("greater(X, f(X), f(f(a))) :- greater(Y, Y, Y).", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
+	}
+
+	#[test]
+	#[timeout(1000)]
+	fn pred_match_infinite_nest_4() {
+		let cyclic = |string| {
+			let (clause, _) = Clause::from_string(string, 0).unwrap();
+			let map = clause.head.match_target(clause.body[0].clone()).unwrap();
+			value(&map.unwrap(), "Y").clone()
+		};
+		let f = cyclic("p(X, X) :- p(Y, f(Y))");
+		let g = cyclic("p(X, X) :- p(Y, g(a, h(Y)))");
+		assert_eq!(g.to_string(), "_S1 where _S1 = g(a, h(_S1))");
+		assert_eq!(g.args()[1].to_string(), "h(_S1) where _S1 = g(a, h(_S1))");
+		let list = cyclic("p(X, X) :- p(Y, [a, b|Y])");
+		assert_eq!(list.to_string(), "_S1 where _S1 = [a, b|_S1]");
+		assert_eq!(list.list_items(), None);
+		let nested = cyclic("p(X, X, Y) :- p(W, f(W), k(W, W))");
+		// each occurrence of a cyclic variable is a copy of its own
+		assert_eq!(
+			nested.to_string(),
+			"k(_S1, _S2) where _S1 = f(_S1), _S2 = f(_S2)"
+		);
+		let operator = cyclic("p(X, X) :- p(Y, Y + 1)");
+		assert_eq!(operator.to_string(), "_S1 where _S1 = _S1 + 1");
+		// unification, copying and comparison end on cycles
+		let map = f.match_target(cyclic("p(X, f(X)) :- p(Y, X)")).unwrap();
+		assert!(map.unwrap().is_empty());
+		assert_eq!(f.match_target(g.clone()), Ok(None));
+		assert_eq!(f.standard_cmp(&f.clone()), Ordering::Equal);
+		assert_eq!(f.standard_cmp(&g), Ordering::Less);
+		assert_eq!(
+			Pred::compound(Symbol::intern("k"), vec![f, g]).to_string(),
+			"k(_S1, _S2) where _S1 = f(_S1), _S2 = g(a, h(_S2))"
+		);
 	}
 
 	#[test]
 	#[timeout(1000)]
 	fn pred_match_more_examples() {
 		let (clause, _) =
-			Clause::from_string("add(s(z), X, s(s(s(z)))) :- add(s(X1), Y1, s(Z1)).", 0);
+			Clause::from_string("add(s(z), X, s(s(s(z)))) :- add(s(X1), Y1, s(Z1)).", 0).unwrap();
 		assert!(clause
 			.head
-			.match_target(clause.body[0].clone(), 0)
+			.match_target(clause.body[0].clone())
+			.unwrap()
 			.is_some());
-		let (clause, _) = Clause::from_string("p(X, f(X, b), f(a, b)) :- p(c, W, W).", 0);
-		assert!(clause
-			.head
-			.match_target(clause.body[0].clone(), 0)
-			.is_none());
+		let (clause, _) = Clause::from_string("p(X, f(X, b), f(a, b)) :- p(c, W, W).", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
 		let (clause, _) = Clause::from_string(
 			"p(X, l(a, n), l(b, l(a, n))) :- p(l(X1, Y1), Z1, l(X1, W1)).",
 			0,
-		);
+		)
+		.unwrap();
 		assert!(clause
 			.head
-			.match_target(clause.body[0].clone(), 0)
+			.match_target(clause.body[0].clone())
+			.unwrap()
 			.is_some());
 		let (clause, _) =
-			Clause::from_string("p(X, f(X, Y), Z) :- p(g(W, T), f(g(a, V), U), c).", 0);
+			Clause::from_string("p(X, f(X, Y), Z) :- p(g(W, T), f(g(a, V), U), c).", 0).unwrap();
 		assert!(clause
 			.head
-			.match_target(clause.body[0].clone(), 0)
+			.match_target(clause.body[0].clone())
+			.unwrap()
 			.is_some());
 	}
 
 	#[test]
 	#[timeout(1000)]
-	fn instmap_compress_nothing_should_not_fail() {
-		let instmap: InstMap = Default::default();
-		instmap_compress(instmap);
+	fn pred_terms() {
+		let (clause, _) = Clause::from_string("f(X, 'Y', y, -2, 1.5, g(_), Neq(a))", 0).unwrap();
+		let args = clause.head.args();
+		assert_eq!(args[0].to_string(), "X");
+		assert!(args[0].is_var());
+		assert_eq!(args[1].root(), &Term::Atom(Symbol::intern("Y")));
+		assert_eq!(args[2].root(), &Term::Atom(Symbol::intern("y")));
+		assert_eq!(args[3].root().get_integer(), Some((-2).into()));
+		assert_eq!(args[4].root().get_number(), Some(Number::Float(1.5)));
+		assert_eq!(args[5].root().arity(), 1);
+		assert!(args[5].args()[0].is_var());
+		assert_eq!(args[6].get_name(), "Neq");
+		assert_eq!(
+			clause.head.to_string(),
+			"f(X, 'Y', y, -2, 1.5, g(_0), Neq(a))"
+		);
+		// a quoted atom never binds like a variable
+		let (clause, _) = Clause::from_string("p('X', 1) :- p(a, 1.0)", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
 	}
 
 	#[test]
 	#[timeout(1000)]
-	fn instmap_compress_vanilla() {
-		let mut instmap: InstMap = Default::default();
-		instmap.insert("A".to_string(), Pred::vc_from_string("a".to_string()));
-		instmap = instmap_compress(instmap);
-		assert_eq!(instmap.get("A").unwrap().nodes[0].ident, "a");
+	fn pred_match_arity_fail() {
+		let (clause, _) = Clause::from_string("p(f(a)) :- p(f(a, b))", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
+		let (clause, _) = Clause::from_string("p(f(X, Y)) :- p(f(a))", 0).unwrap();
+		assert_eq!(clause.head.match_target(clause.body[0].clone()), Ok(None));
 	}
 
 	#[test]
-	#[timeout(1000)]
-	fn instmap_compress_2() {
-		let mut instmap: InstMap = Default::default();
-		instmap.insert("D".to_string(), Pred::vc_from_string("a".to_string()));
-		instmap.insert("A".to_string(), Pred::vc_from_string("B".to_string()));
-		instmap.insert("C".to_string(), Pred::vc_from_string("D".to_string()));
-		instmap.insert("B".to_string(), Pred::vc_from_string("D".to_string()));
-		instmap = instmap_compress(instmap);
-		assert_eq!(instmap.get("A").unwrap().nodes[0].ident, "a");
+	fn instantiate_neq_fail() {
+		let (clause, _) = Clause::from_string("Neq(A, B).", 0).unwrap();
+		let instmap: InstMap = clause
+			.head
+			.variables()
+			.into_iter()
+			.map(|var| (var, Pred::leaf(Term::Atom(Symbol::intern("a")))))
+			.collect();
+		assert_eq!(clause.head.instantiate(&instmap), None);
 	}
 
 	#[test]
-	fn instantiate_neq_fail() {
-		let (clause, _) = Clause::from_string("Neq(A, B).", 0);
-		let mut instmap: InstMap = Default::default();
-		instmap.insert("A".to_string(), Pred::vc_from_string("a".to_string()));
-		instmap.insert("B".to_string(), Pred::vc_from_string("a".to_string()));
-		assert_eq!(clause.head.instantiate(&instmap), None);
+	#[timeout(1000)]
+	fn pred_canonical() {
+		let (clause, _) = Clause::from_string("p(Y, f(Z, Y)) :- p(A, f(B, A)).", 0).unwrap();
+		let canonical = clause.head.canonical();
+		// variants are equal, the variables are not interned as symbols
+		assert_eq!(canonical, clause.body[0].canonical());
+		assert_eq!(canonical.to_string(), "p(_0, f(_1, _0))");
+		assert!(canonical.variables().iter().all(|var| var.name.is_none()));
 	}
 }
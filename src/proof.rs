@@ -0,0 +1,162 @@
+use crate::pred::{instmap_to_string, InstMap, Pred};
+
+// derivation of one goal, children follow the clause body order
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProofTree {
+	// fully instantiated by the whole successful branch
+	pub goal: Pred,
+	// the resolving clause is Theory::get_clause(goal name, arity, rule_id),
+	// or the rule_id-th answer of the table for a tabled goal
+	pub rule_id: usize,
+	// substitution of this resolution step
+	pub instmap: InstMap,
+	pub children: Vec<ProofTree>,
+}
+
+// one resolution step of a successful branch, in the order they were taken
+pub(crate) struct ProofStep {
+	pub goal: Pred,
+	pub rule_id: usize,
+	pub instmap: InstMap,
+	// number of body goals that became subproofs
+	pub arity: usize,
+	// built-in step, its subproofs belong to the parent
+	pub transparent: bool,
+}
+
+impl ProofTree {
+	// steps are a preorder walk of the proof forest
+	pub(crate) fn build_forest(steps: Vec<ProofStep>) -> Vec<ProofTree> {
+		fn build<I: Iterator<Item = ProofStep>>(steps: &mut I) -> Option<Vec<ProofTree>> {
+			let step = steps.next()?;
+			let children = (0..step.arity)
+				.filter_map(|_| build(steps))
+				.flatten()
+				.collect();
+			if step.transparent {
+				return Some(children);
+			}
+			Some(vec![ProofTree {
+				goal: step.goal,
+				rule_id: step.rule_id,
+				instmap: step.instmap,
+				children,
+			}])
+		}
+
+		let mut steps = steps.into_iter();
+		let mut result = Vec::new();
+		while let Some(trees) = build(&mut steps) {
+			result.extend(trees);
+		}
+		result
+	}
+
+	fn to_string_recurse(&self, result: &mut String, level: usize) {
+		for _ in 0..level {
+			result.push_str("  ");
+		}
+		let line = format!(
+			"{} [{}] {}",
+			self.goal,
+			self.rule_id,
+			instmap_to_string(&self.instmap)
+		);
+		result.push_str(line.trim_end());
+		result.push('\n');
+		for child in self.children.iter() {
+			child.to_string_recurse(result, level + 1);
+		}
+	}
+}
+
+impl std::fmt::Display for ProofTree {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let mut result = String::new();
+		self.to_string_recurse(&mut result, 0);
+		write!(f, "{}", result.trim_end())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::ProofTree;
+	use crate::theory::Theory;
+
+	// goal[children, ...]
+	fn shape(tree: &ProofTree) -> String {
+		if tree.children.is_empty() {
+			return tree.goal.to_string();
+		}
+		let children: Vec<String> = tree.children.iter().map(shape).collect();
+		format!("{}[{}]", tree.goal, children.join(", "))
+	}
+
+	#[test]
+	fn proof_partial_order() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"greater(two, one).
+			greater(three, two).
+			greater(A, C) :- greater(A, B), greater(B, C).
+			",
+			)
+			.unwrap();
+		let mut solutions = theory.query("greater(three, one)", 32).unwrap();
+		assert!(solutions.proof().is_empty());
+		solutions.next().unwrap();
+		let proof = solutions.proof();
+		assert_eq!(proof.len(), 1);
+		assert_eq!(proof[0].goal.to_string(), "greater(three, one)");
+		assert_eq!(proof[0].rule_id, 2);
+		let children: Vec<(String, usize)> = proof[0]
+			.children
+			.iter()
+			.map(|x| (x.goal.to_string(), x.rule_id))
+			.collect();
+		assert_eq!(
+			children,
+			vec![
+				("greater(three, two)".to_string(), 1),
+				("greater(two, one)".to_string(), 0)
+			]
+		);
+		assert!(proof[0].children.iter().all(|x| x.children.is_empty()));
+		assert_eq!(
+			theory
+				.get_clause("greater", 2, proof[0].rule_id)
+				.unwrap()
+				.to_string(),
+			"greater(A, C) :- greater(A, B), greater(B, C)"
+		);
+	}
+
+	#[test]
+	fn proof_builtins() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"q(X) :- r(X, Y), s(Y).
+			r(X, Y) :- Y is X + 1, !, p(Y).
+			r(X, Y) :- p(Y).
+			s(Y) :- \\+ t(Y), Y = Z, p(Z).
+			t(3).
+			p(2).
+			p(3).
+			u(X) :- (p(X) -> true ; X = b).
+			",
+			)
+			.unwrap();
+		// built-ins other than control constructs have no subproofs
+		let mut solutions = theory.query("q(1)", 32).unwrap();
+		solutions.next().unwrap();
+		let proof: Vec<String> = solutions.proof().iter().map(shape).collect();
+		assert_eq!(proof, vec!["q(1)[r(1, 2)[p(2)], s(2)[p(2)]]"]);
+		// true ends the branch of the if-then-else
+		let mut solutions = theory.query("u(X)", 32).unwrap();
+		solutions.next().unwrap();
+		let proof: Vec<String> = solutions.proof().iter().map(shape).collect();
+		assert_eq!(proof, vec!["u(2)[p(2)]"]);
+	}
+}
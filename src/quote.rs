@@ -0,0 +1,188 @@
+use crate::error::ParseErrorKind;
+
+pub(crate) fn is_symbol_char(ch: char) -> bool {
+	"-+*/\\^<>=~:?@#&$.".contains(ch)
+}
+
+// the text of an atom written without quotes reads back as the same atom
+fn needs_quotes(text: &str) -> bool {
+	let mut chars = text.chars();
+	match chars.next() {
+		None => true,
+		Some('a'..='z') => !chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_'),
+		Some(ch) if is_symbol_char(ch) => {
+			!text.chars().all(is_symbol_char) || text.starts_with("/*") || text == "."
+		}
+		_ => !matches!(text, "!" | ";" | "[]" | "{}"),
+	}
+}
+
+// text between quote characters, with escape sequences for the quote itself,
+// backslashes and control characters
+fn escape(text: &str, quote: char) -> String {
+	let mut result = String::new();
+	result.push(quote);
+	for ch in text.chars() {
+		match ch {
+			'\\' => result.push_str("\\\\"),
+			'\n' => result.push_str("\\n"),
+			'\t' => result.push_str("\\t"),
+			ch if ch == quote => {
+				result.push('\\');
+				result.push(ch);
+			}
+			ch if ch.is_control() => result.push_str(&format!("\\x{:x}\\", ch as u32)),
+			ch => result.push(ch),
+		}
+	}
+	result.push(quote);
+	result
+}
+
+// identifier of the atom with text, quoted only when needed
+pub(crate) fn atom_ident(text: &str) -> String {
+	if needs_quotes(text) {
+		escape(text, '\'')
+	} else {
+		text.to_string()
+	}
+}
+
+// identifier of a functor name, a variable name followed by arguments is read
+// as a functor, as in Neq(X, Y)
+pub(crate) fn functor_ident(text: &str) -> String {
+	let mut chars = text.chars();
+	if chars.next().is_some_and(|ch| ch.is_ascii_uppercase())
+		&& chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+	{
+		text.to_string()
+	} else {
+		atom_ident(text)
+	}
+}
+
+// identifier of a string object, always double quoted
+pub(crate) fn string_ident(text: &str) -> String {
+	escape(text, '"')
+}
+
+// the character a \ escape stands for, None for a continuation line
+fn unescape(chars: &mut std::iter::Peekable<std::str::CharIndices>) -> Result<Option<char>, ()> {
+	let (_, ch) = chars.next().ok_or(())?;
+	let code =
+		|chars: &mut std::iter::Peekable<std::str::CharIndices>, first: Option<char>, radix| {
+			let mut digits: String = first.into_iter().collect();
+			loop {
+				match chars.next() {
+					Some((_, '\\')) => break,
+					Some((_, ch)) if ch.is_digit(radix) => digits.push(ch),
+					_ => return Err(()),
+				}
+			}
+			u32::from_str_radix(&digits, radix)
+				.ok()
+				.and_then(char::from_u32)
+				.ok_or(())
+		};
+	Ok(Some(match ch {
+		'n' => '\n',
+		't' => '\t',
+		'r' => '\r',
+		'a' => '\x07',
+		'b' => '\x08',
+		'f' => '\x0c',
+		'v' => '\x0b',
+		'e' => '\x1b',
+		'0'..='7' => code(chars, Some(ch), 8)?,
+		'x' => code(chars, None, 16)?,
+		'\\' | '\'' | '"' | '`' => ch,
+		'\n' => return Ok(None),
+		_ => return Err(()),
+	}))
+}
+
+// length and text of the quoted item text starts with, the error comes with
+// the range of the offending part
+pub(crate) fn scan(text: &str) -> Result<(usize, String), (ParseErrorKind, usize, usize)> {
+	let quote = text.chars().next().unwrap();
+	let mut result = String::new();
+	let mut chars = text.char_indices().peekable();
+	chars.next();
+	while let Some((i, ch)) = chars.next() {
+		match ch {
+			'\\' => match unescape(&mut chars) {
+				Ok(Some(ch)) => result.push(ch),
+				Ok(None) => {}
+				Err(()) => {
+					let end = chars.peek().map_or(text.len(), |(end, _)| *end);
+					return Err((ParseErrorKind::InvalidEscape, i, end));
+				}
+			},
+			ch if ch == quote => {
+				// a doubled quote stands for itself
+				if chars.peek().map(|(_, next)| *next) == Some(quote) {
+					chars.next();
+					result.push(quote);
+				} else {
+					return Ok((i + 1, result));
+				}
+			}
+			ch => result.push(ch),
+		}
+	}
+	Err((ParseErrorKind::UnterminatedQuoted, 0, text.len()))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn quote_atoms() {
+		for (text, ident) in [
+			("abc", "abc"),
+			("aB_1", "aB_1"),
+			("New York", "'New York'"),
+			("hello world", "'hello world'"),
+			("", "''"),
+			("=..", "=.."),
+			(".", "'.'"),
+			("\\+", "\\+"),
+			("12", "'12'"),
+			("[]", "[]"),
+			(",", "','"),
+			("it's", "'it\\'s'"),
+			("a\nb\\", "'a\\nb\\\\'"),
+			("été", "'été'"),
+			("/*", "'/*'"),
+		]
+		.iter()
+		{
+			assert_eq!(atom_ident(text), *ident);
+		}
+		assert_eq!(functor_ident("Neq"), "Neq");
+		assert_eq!(functor_ident("x y"), "'x y'");
+		assert_eq!(string_ident("say \"hi\""), "\"say \\\"hi\\\"\"");
+	}
+
+	#[test]
+	fn quote_scan() {
+		assert_eq!(scan("'abc' rest"), Ok((5, "abc".to_string())));
+		assert_eq!(scan("'it''s'"), Ok((7, "it's".to_string())));
+		assert_eq!(
+			scan(r#"'a\n\t\x41\\101\\\\'\"'"#),
+			Ok((23, "a\n\tAA\\'\"".to_string()))
+		);
+		assert_eq!(scan("\"one \\\ntwo\""), Ok((11, "one two".to_string())));
+		assert_eq!(scan("`a'b`"), Ok((5, "a'b".to_string())));
+		assert_eq!(
+			scan("'abc"),
+			Err((ParseErrorKind::UnterminatedQuoted, 0, 4))
+		);
+		assert_eq!(scan("'a\\qb'"), Err((ParseErrorKind::InvalidEscape, 2, 4)));
+		assert_eq!(
+			scan("'\\x4g\\'").unwrap_err().0,
+			ParseErrorKind::InvalidEscape
+		);
+	}
+}
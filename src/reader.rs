@@ -0,0 +1,310 @@
+use std::io::{BufRead, BufReader, Read};
+
+use crate::error::{ParseError, ParseErrorKind, ReadError, Span};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+	Code,
+	LineComment,
+	// with its nesting depth
+	BlockComment(usize),
+	// inside a quoted item, with its quote character
+	Quoted(char),
+}
+
+fn is_symbol_char(ch: char) -> bool {
+	"-+*/\\^<>=~:?@#&$.".contains(ch)
+}
+
+// byte length of the comment text starts with and whether it is closed,
+// block comments nest and an unclosed one extends to the end of text
+pub(crate) fn comment_len(text: &str) -> Option<(usize, bool)> {
+	if text.starts_with('%') {
+		return Some((text.find('\n').unwrap_or(text.len()), true));
+	}
+	if !text.starts_with("/*") {
+		return None;
+	}
+	let mut depth = 0;
+	let mut i = 0;
+	while i < text.len() {
+		if text[i..].starts_with("/*") {
+			depth += 1;
+			i += 2;
+		} else if text[i..].starts_with("*/") {
+			depth -= 1;
+			i += 2;
+			if depth == 0 {
+				return Some((i, true));
+			}
+		} else {
+			i += text[i..].chars().next().unwrap().len_utf8();
+		}
+	}
+	Some((text.len(), false))
+}
+
+// text without its leading layout and comments
+pub(crate) fn skip_layout(mut text: &str) -> &str {
+	text = text.trim_start();
+	while let Some((len, _)) = comment_len(text) {
+		text = text[len..].trim_start();
+	}
+	text
+}
+
+// the last documentation comment before the code of a clause, a %! line with
+// the % lines following it, or a /** */ block
+pub(crate) fn doc_comment(text: &str) -> Option<String> {
+	let mut result: Option<String> = None;
+	let mut lines = false;
+	let mut text = text.trim_start();
+	while let Some((len, _)) = comment_len(text) {
+		let comment = &text[..len];
+		if let Some(line) = comment.strip_prefix("%!") {
+			result = Some(line.trim().to_string());
+			lines = true;
+		} else if let Some(line) = comment.strip_prefix('%').filter(|_| lines) {
+			let doc = result.as_mut().unwrap();
+			doc.push('\n');
+			doc.push_str(line.trim());
+		} else if comment.starts_with("/**") && comment.len() > "/**/".len() {
+			let inner = comment[3..].trim_end_matches("*/");
+			let inner: Vec<&str> = inner
+				.lines()
+				.map(|line| line.trim().trim_start_matches('*').trim())
+				.collect();
+			result = Some(inner.join("\n").trim().to_string());
+			lines = false;
+		} else {
+			lines = false;
+		}
+		text = text[len..].trim_start();
+	}
+	result
+}
+
+// splits the text of a theory into clauses, without their terminating `.`,
+// while reading it line by line
+pub struct ClauseReader<R> {
+	input: BufReader<R>,
+	// text not yet returned, starting at position in the input
+	buffer: String,
+	position: Span,
+	// bytes of buffer already scanned, in state
+	scanned: usize,
+	state: State,
+	previous: Option<char>,
+	// some code other than layout and comments since the last clause
+	content: bool,
+	eof: bool,
+}
+
+impl<R: Read> ClauseReader<R> {
+	pub fn new(input: R) -> ClauseReader<R> {
+		ClauseReader {
+			input: BufReader::new(input),
+			buffer: String::new(),
+			position: Span {
+				start: 0,
+				end: 0,
+				line: 1,
+				column: 1,
+			},
+			scanned: 0,
+			state: State::Code,
+			previous: None,
+			content: false,
+			eof: false,
+		}
+	}
+
+	// byte position of the end token `.` in buffer, a `.` that is not part of
+	// a symbol-char token and is followed by layout, a % comment or the input end
+	fn find_end(&mut self) -> Option<usize> {
+		let mut chars = self.buffer[self.scanned..].char_indices().peekable();
+		while let Some((i, ch)) = chars.next() {
+			let i = self.scanned + i;
+			let next = chars.peek().map(|(_, next)| *next);
+			if next.is_none() && !self.eof {
+				// needs a look at the next line
+				self.scanned = i;
+				return None;
+			}
+			let mut current = ch;
+			match self.state {
+				State::Code => match ch {
+					'.' if !self.previous.is_some_and(is_symbol_char)
+						&& next.is_none_or(|next| next.is_whitespace() || next == '%') =>
+					{
+						self.scanned = i + 1;
+						return Some(i);
+					}
+					'%' => self.state = State::LineComment,
+					'/' if next == Some('*') => {
+						chars.next();
+						self.state = State::BlockComment(1);
+					}
+					'\'' | '"' | '`' => {
+						self.content = true;
+						self.state = State::Quoted(ch);
+					}
+					_ => self.content |= !ch.is_whitespace(),
+				},
+				State::LineComment => {
+					if ch == '\n' {
+						self.state = State::Code;
+					}
+				}
+				State::BlockComment(depth) => {
+					if ch == '/' && next == Some('*') {
+						chars.next();
+						self.state = State::BlockComment(depth + 1);
+					} else if ch == '*' && next == Some('/') {
+						chars.next();
+						self.state = if depth == 1 {
+							State::Code
+						} else {
+							State::BlockComment(depth - 1)
+						};
+					}
+				}
+				State::Quoted(quote) => {
+					if ch == '\\' || (ch == quote && next == Some(quote)) {
+						// escaped character or doubled quote
+						if let Some((_, next)) = chars.next() {
+							current = next;
+						}
+					} else if ch == quote {
+						self.state = State::Code;
+					}
+				}
+			}
+			// a comment separates tokens
+			self.previous = if self.state == State::Code {
+				Some(current)
+			} else {
+				None
+			};
+		}
+		self.scanned = self.buffer.len();
+		None
+	}
+
+	// removes the first len bytes of buffer, returning them with their span
+	fn take(&mut self, len: usize) -> (String, Span) {
+		let text: String = self.buffer.drain(..len).collect();
+		let span = Span {
+			end: self.position.start + len,
+			..self.position
+		};
+		self.scanned -= len;
+		self.position.start += len;
+		for ch in text.chars() {
+			if ch == '\n' {
+				self.position.line += 1;
+				self.position.column = 1;
+			} else {
+				self.position.column += 1;
+			}
+		}
+		(text, span)
+	}
+}
+
+impl<R: Read> Iterator for ClauseReader<R> {
+	type Item = Result<(String, Span), ReadError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(end) = self.find_end() {
+				let clause = self.take(end);
+				self.take(1);
+				self.previous = None;
+				self.content = false;
+				return Some(Ok(clause));
+			}
+			if self.eof {
+				if !self.content {
+					return None;
+				}
+				// reported once, the reader is empty afterwards
+				self.content = false;
+				let len = self.buffer.len();
+				let (clause, span) = self.take(len);
+				return Some(Err(ReadError::Parse(vec![ParseError {
+					kind: ParseErrorKind::UnterminatedClause,
+					clause,
+					span,
+				}])));
+			}
+			match self.input.read_line(&mut self.buffer) {
+				Ok(0) => self.eof = true,
+				Ok(_) => {}
+				Err(error) => return Some(Err(ReadError::Io(error))),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn clauses(text: &str) -> Vec<String> {
+		ClauseReader::new(text.as_bytes())
+			.map(|clause| clause.unwrap().0.trim().to_string())
+			.collect()
+	}
+
+	#[test]
+	fn read_clauses() {
+		assert_eq!(
+			clauses("a(1.5). b :- c.\nd(X) :- X =.. Y.% e.f\ng('x. y', \"z.\"). /* h. */"),
+			vec![
+				"a(1.5)",
+				"b :- c",
+				"d(X) :- X =.. Y",
+				"% e.f\ng('x. y', \"z.\")"
+			]
+		);
+		assert_eq!(clauses("a.b. c.\n\n"), vec!["a.b", "c"]);
+		assert_eq!(clauses("'it''s.' .  "), vec!["'it''s.'"]);
+		let spans: Vec<Span> = ClauseReader::new("a.\n  b(c)\n.".as_bytes())
+			.map(|clause| clause.unwrap().1)
+			.collect();
+		assert_eq!((spans[1].start, spans[1].end), (2, 10));
+		assert_eq!((spans[1].line, spans[1].column), (1, 3));
+		let mut reader = ClauseReader::new("a. % done\nb(c) :- d".as_bytes());
+		assert_eq!(reader.next().unwrap().unwrap().0, "a");
+		let errors = match reader.next() {
+			Some(Err(ReadError::Parse(errors))) => errors,
+			result => panic!("{:?}", result),
+		};
+		assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedClause);
+		assert_eq!(errors[0].clause, " % done\nb(c) :- d");
+		assert!(reader.next().is_none());
+		assert_eq!(
+			clauses("a /* b. /* c. */ d. */ (e). f. /* g. */"),
+			vec!["a /* b. /* c. */ d. */ (e)", "f"]
+		);
+	}
+
+	#[test]
+	fn read_comments() {
+		assert_eq!(comment_len("% a. b\nc"), Some((6, true)));
+		assert_eq!(comment_len("/* a /* b */ c */ d"), Some((17, true)));
+		assert_eq!(comment_len("/* a /* b */ c"), Some((14, false)));
+		assert_eq!(comment_len("a % b"), None);
+		assert_eq!(skip_layout(" % a\n /* b */ c(d) % e"), "c(d) % e");
+		assert_eq!(
+			doc_comment("\n%! f(+X)\n%  X is a number.\n\nf(X)").as_deref(),
+			Some("f(+X)\nX is a number.")
+		);
+		assert_eq!(
+			doc_comment("/**\n * g(?X)\n *\n * Holds for any X.\n */\n% plain\ng(_)").as_deref(),
+			Some("g(?X)\n\nHolds for any X.")
+		);
+		assert_eq!(doc_comment("% plain\n/* block */\nh(a)"), None);
+	}
+}
@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+
+use crate::clause::Clause;
+use crate::pred::{InstMap, Pred};
+use crate::theory::Answer;
+use crate::tracer::Tracer;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeState {
+	// has been expanded into children
+	Open,
+	Succeed,
+	Fail,
+	DepthExceed,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchNode {
+	// goal list, empty for a success leaf
+	pub targets: Vec<Pred>,
+	pub parent: Option<usize>,
+	// clause resolving the parent's first target into this node
+	pub clause: Option<Clause>,
+	pub state: NodeState,
+}
+
+// records the explored SLD tree when used as a tracer
+#[derive(Clone, Debug, Default)]
+pub struct SearchTree {
+	pub nodes: Vec<SearchNode>,
+	// node of each frame of the search stack
+	stack: Vec<usize>,
+	// matched but not yet entered
+	pending: Option<Clause>,
+	// a node with no child when its frame fails is a failure leaf
+	children_count: Vec<usize>,
+}
+
+impl SearchTree {
+	fn push_node(&mut self, targets: Vec<Pred>, state: NodeState) -> usize {
+		let parent = self.stack.last().cloned();
+		if let Some(parent) = parent {
+			self.children_count[parent] += 1;
+		}
+		self.nodes.push(SearchNode {
+			targets,
+			parent,
+			clause: self.pending.take(),
+			state,
+		});
+		self.children_count.push(0);
+		self.nodes.len() - 1
+	}
+
+	pub fn to_dot(&self) -> String {
+		fn escape(string: String) -> String {
+			string.replace('\\', "\\\\").replace('"', "\\\"")
+		}
+
+		let mut result = "digraph sld {\n\tnode [shape=box];\n".to_string();
+		for (id, node) in self.nodes.iter().enumerate() {
+			let label = if node.targets.is_empty() {
+				"success".to_string()
+			} else {
+				node.targets
+					.iter()
+					.map(|x| x.to_string())
+					.collect::<Vec<String>>()
+					.join(", ")
+			};
+			let color = match node.state {
+				NodeState::Open => "",
+				NodeState::Succeed => ", style=filled, fillcolor=palegreen",
+				NodeState::Fail => ", style=filled, fillcolor=lightpink",
+				NodeState::DepthExceed => ", style=filled, fillcolor=orange",
+			};
+			result += &format!("\tn{} [label=\"{}\"{}];\n", id, escape(label), color);
+			if let Some(parent) = node.parent {
+				let clause = match &node.clause {
+					None => String::new(),
+					Some(clause) => clause.to_string(),
+				};
+				result += &format!("\tn{} -> n{} [label=\"{}\"];\n", parent, id, escape(clause));
+			}
+		}
+		result += "}\n";
+		result
+	}
+}
+
+impl Tracer for SearchTree {
+	fn call(&mut self, _target: &Pred, targets: &VecDeque<Pred>) {
+		let id = self.push_node(targets.iter().cloned().collect(), NodeState::Open);
+		self.stack.push(id);
+	}
+
+	fn matched(&mut self, _target: &Pred, clause: &Clause, _instmap: &InstMap) {
+		self.pending = Some(clause.clone());
+	}
+
+	fn fail(&mut self, _target: &Pred) {
+		self.pending = None;
+		let id = self.stack.pop().unwrap();
+		if self.nodes[id].state == NodeState::Open && self.children_count[id] == 0 {
+			self.nodes[id].state = NodeState::Fail;
+		}
+	}
+
+	fn depth_exceed(&mut self, _target: &Pred) {
+		let id = *self.stack.last().unwrap();
+		self.nodes[id].state = NodeState::DepthExceed;
+	}
+
+	fn succeed(&mut self, _answer: &Answer) {
+		self.push_node(Vec::new(), NodeState::Succeed);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::theory::Theory;
+
+	#[test]
+	fn search_tree_states() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"parent(a, b).
+			parent(b, c).
+			ancestor(X, Y) :- ancestor(X, Z), parent(Z, Y).
+			ancestor(X, Y) :- parent(X, Y).
+			",
+			)
+			.unwrap();
+		let mut tree: SearchTree = Default::default();
+		let count = theory
+			.query("ancestor(a, c)", 4)
+			.unwrap()
+			.with_tracer(&mut tree)
+			.count();
+		assert_eq!(count, 1);
+		let count_state = |state| tree.nodes.iter().filter(|x| x.state == state).count();
+		assert_eq!(count_state(NodeState::Succeed), 1);
+		assert!(count_state(NodeState::Fail) > 0);
+		assert!(count_state(NodeState::DepthExceed) > 0);
+		assert_eq!(tree.nodes[0].targets[0].to_string(), "ancestor(a, c)");
+		assert!(tree.nodes.iter().skip(1).all(|x| x.clause.is_some()));
+		let dot = tree.to_dot();
+		assert!(dot.starts_with("digraph sld {"));
+		assert!(dot.contains("n0 [label=\"ancestor(a, c)\"];"));
+		assert!(dot.contains("fillcolor=palegreen"));
+		assert!(dot.contains("fillcolor=lightpink"));
+		assert!(dot.contains("fillcolor=orange"));
+		assert!(
+			dot.contains("n0 -> n1 [label=\"ancestor(X, Y) :- ancestor(X, Z), parent(Z, Y)\"];")
+		);
+	}
+}
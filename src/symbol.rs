@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use crate::quote;
+
+// handle of an interned atom or functor name, names are compared by handle
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+// handle of an interned name and arity pair
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Functor(u32);
+
+// interned when the table is created, in the order of the Symbol constants
+const SYMBOLS: [&str; 4] = ["[]", "[|]", "!", "true"];
+
+// interned when the table is created, in the order of the Functor constants
+const FUNCTORS: [(&str, usize); 38] = [
+	("[|]", 2),
+	("!", 1),
+	("$soft_cut", 1),
+	(",", 2),
+	(";", 2),
+	("->", 2),
+	("*->", 2),
+	("\\+", 1),
+	("not", 1),
+	("is", 2),
+	("<", 2),
+	("=<", 2),
+	(">", 2),
+	(">=", 2),
+	("=:=", 2),
+	("=\\=", 2),
+	("==", 2),
+	("\\==", 2),
+	("@<", 2),
+	("@=<", 2),
+	("@>", 2),
+	("@>=", 2),
+	("compare", 3),
+	("=", 2),
+	("\\=", 2),
+	("unify_with_occurs_check", 2),
+	("var", 1),
+	("nonvar", 1),
+	("atom", 1),
+	("number", 1),
+	("integer", 1),
+	("float", 1),
+	("atomic", 1),
+	("compound", 1),
+	("is_list", 1),
+	("msort", 2),
+	("sort", 2),
+	("Neq", 2),
+];
+
+struct Table {
+	symbols: HashMap<&'static str, Symbol>,
+	names: Vec<&'static str>,
+	functors: HashMap<(Symbol, usize), Functor>,
+	pairs: Vec<(Symbol, usize)>,
+}
+
+// shared by every theory, names live as long as the program
+fn table() -> &'static RwLock<Table> {
+	static TABLE: OnceLock<RwLock<Table>> = OnceLock::new();
+	TABLE.get_or_init(|| {
+		let mut table = Table {
+			symbols: HashMap::new(),
+			names: Vec::new(),
+			functors: HashMap::new(),
+			pairs: Vec::new(),
+		};
+		for name in SYMBOLS.iter() {
+			table.symbol(name);
+		}
+		for (id, (name, arity)) in FUNCTORS.iter().enumerate() {
+			let name = table.symbol(name);
+			if PRIVATE.contains(&Functor(id as u32)) {
+				table.pairs.push((name, *arity));
+			} else {
+				table.functor(name, *arity);
+			}
+		}
+		RwLock::new(table)
+	})
+}
+
+// written like the functors of the same name and arity but never read, the
+// terms of the engine users cannot write
+const PRIVATE: [Functor; 2] = [Functor::CUT, Functor::SOFT_CUT];
+
+impl Table {
+	fn symbol(&mut self, name: &str) -> Symbol {
+		if let Some(symbol) = self.symbols.get(name) {
+			return *symbol;
+		}
+		let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+		let symbol = Symbol(self.names.len() as u32);
+		self.names.push(name);
+		self.symbols.insert(name, symbol);
+		symbol
+	}
+
+	fn functor(&mut self, name: Symbol, arity: usize) -> Functor {
+		if let Some(functor) = self.functors.get(&(name, arity)) {
+			return *functor;
+		}
+		let functor = Functor(self.pairs.len() as u32);
+		self.pairs.push((name, arity));
+		self.functors.insert((name, arity), functor);
+		functor
+	}
+}
+
+impl Symbol {
+	// the empty list []
+	pub const NIL: Symbol = Symbol(0);
+	// the list constructor '[|]'
+	pub const CONS: Symbol = Symbol(1);
+	pub(crate) const CUT: Symbol = Symbol(2);
+	pub(crate) const TRUE: Symbol = Symbol(3);
+
+	// the text of the name, quotes are only added when it is written
+	pub fn intern(name: &str) -> Symbol {
+		if let Some(symbol) = table().read().unwrap().symbols.get(name) {
+			return *symbol;
+		}
+		table().write().unwrap().symbol(name)
+	}
+
+	pub fn name(self) -> &'static str {
+		table().read().unwrap().names[self.0 as usize]
+	}
+}
+
+impl Functor {
+	// '[|]'/2
+	pub const CONS: Functor = Functor(0);
+	// the built-in predicates, compared by handle when resolving
+	// cut back to the frame of its argument, private
+	pub(crate) const CUT: Functor = Functor(1);
+	// marks the end of the condition of a soft-cut, private
+	pub(crate) const SOFT_CUT: Functor = Functor(2);
+	pub(crate) const AND: Functor = Functor(3);
+	pub(crate) const OR: Functor = Functor(4);
+	pub(crate) const IF_THEN: Functor = Functor(5);
+	pub(crate) const SOFT_IF_THEN: Functor = Functor(6);
+	pub(crate) const NEGATION: Functor = Functor(7);
+	pub(crate) const NOT: Functor = Functor(8);
+	pub(crate) const IS: Functor = Functor(9);
+	pub(crate) const LESS: Functor = Functor(10);
+	pub(crate) const LESS_EQUAL: Functor = Functor(11);
+	pub(crate) const GREATER: Functor = Functor(12);
+	pub(crate) const GREATER_EQUAL: Functor = Functor(13);
+	pub(crate) const EQUAL: Functor = Functor(14);
+	pub(crate) const NOT_EQUAL: Functor = Functor(15);
+	pub(crate) const IDENTICAL: Functor = Functor(16);
+	pub(crate) const NOT_IDENTICAL: Functor = Functor(17);
+	pub(crate) const TERM_LESS: Functor = Functor(18);
+	pub(crate) const TERM_LESS_EQUAL: Functor = Functor(19);
+	pub(crate) const TERM_GREATER: Functor = Functor(20);
+	pub(crate) const TERM_GREATER_EQUAL: Functor = Functor(21);
+	pub(crate) const COMPARE: Functor = Functor(22);
+	pub(crate) const UNIFY: Functor = Functor(23);
+	pub(crate) const NOT_UNIFY: Functor = Functor(24);
+	pub(crate) const UNIFY_WITH_OCCURS_CHECK: Functor = Functor(25);
+	pub(crate) const VAR: Functor = Functor(26);
+	pub(crate) const NONVAR: Functor = Functor(27);
+	pub(crate) const ATOM: Functor = Functor(28);
+	pub(crate) const NUMBER: Functor = Functor(29);
+	pub(crate) const INTEGER: Functor = Functor(30);
+	pub(crate) const FLOAT: Functor = Functor(31);
+	pub(crate) const ATOMIC: Functor = Functor(32);
+	pub(crate) const COMPOUND: Functor = Functor(33);
+	pub(crate) const IS_LIST: Functor = Functor(34);
+	pub(crate) const MSORT: Functor = Functor(35);
+	pub(crate) const SORT: Functor = Functor(36);
+	pub(crate) const NEQ: Functor = Functor(37);
+
+	pub fn new(name: Symbol, arity: usize) -> Functor {
+		if let Some(functor) = table().read().unwrap().functors.get(&(name, arity)) {
+			return *functor;
+		}
+		table().write().unwrap().functor(name, arity)
+	}
+
+	pub fn name(self) -> Symbol {
+		table().read().unwrap().pairs[self.0 as usize].0
+	}
+
+	pub fn arity(self) -> usize {
+		table().read().unwrap().pairs[self.0 as usize].1
+	}
+}
+
+// quoted where needed
+impl std::fmt::Display for Symbol {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", quote::atom_ident(self.name()))
+	}
+}
+
+impl std::fmt::Debug for Symbol {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{:?}", self.name())
+	}
+}
+
+impl std::fmt::Debug for Functor {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}/{}", self.name(), self.arity())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn symbol_intern() {
+		let a = Symbol::intern("symbol_test_a");
+		assert_eq!(a, Symbol::intern("symbol_test_a"));
+		assert_ne!(a, Symbol::intern("symbol_test_b"));
+		assert_eq!(a.name(), "symbol_test_a");
+		assert_eq!(Symbol::intern("[]"), Symbol::NIL);
+		assert_eq!(Symbol::CONS.name(), "[|]");
+		assert_eq!(Symbol::intern("New York").to_string(), "'New York'");
+		let f = Functor::new(a, 2);
+		assert_eq!(f, Functor::new(Symbol::intern("symbol_test_a"), 2));
+		assert_ne!(f, Functor::new(a, 1));
+		assert_eq!((f.name(), f.arity()), (a, 2));
+		assert_eq!(Functor::new(Symbol::CONS, 2), Functor::CONS);
+		assert_eq!(format!("{:?}", f), "symbol_test_a/2");
+		assert_eq!(format!("{:?}", Functor::CONS), "'[|]'/2");
+		assert_eq!(Symbol::intern("true"), Symbol::TRUE);
+		for (id, (name, arity)) in FUNCTORS.iter().enumerate() {
+			let functor = Functor(id as u32);
+			let read = Functor::new(Symbol::intern(name), *arity);
+			assert_eq!(read == functor, !PRIVATE.contains(&functor));
+			assert_eq!((functor.name().name(), functor.arity()), (*name, *arity));
+		}
+	}
+}
@@ -0,0 +1,270 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+
+use crate::clause::Clause;
+use crate::error::ProveError;
+use crate::pred::{InstMap, Pred};
+use crate::theory::{Solutions, Theory};
+
+// answers of one call variant, stored as facts
+#[derive(Default)]
+struct Table {
+	answers: Vec<Clause>,
+	keys: HashSet<String>,
+	// answers found before the current pass, those its consumers see, so that
+	// each pass adds one level of answers
+	visible: usize,
+	complete: bool,
+	// the iteration was cut off by dmax, the answers of the complete table are
+	// not all of them
+	truncated: bool,
+	// position in Tables::stack while being evaluated
+	stack_index: Option<usize>,
+	// lowest stack position of an incomplete table this one consumed
+	low: usize,
+	// Tables::pass of the last evaluation
+	pass: usize,
+	// incomplete tables completed together with this one as scc leader
+	members: Vec<String>,
+}
+
+// answer tables shared by a query and all the evaluations it triggers
+#[derive(Default)]
+pub(crate) struct Tables {
+	tables: HashMap<String, Table>,
+	// variants being evaluated, innermost last
+	stack: Vec<String>,
+	answer_count: usize,
+	// bumped whenever a leader iterates again, older incomplete tables are stale
+	pass: usize,
+}
+
+impl Tables {
+	pub fn answer(&self, key: &str, rule_id: usize) -> Option<Clause> {
+		let table = self.tables.get(key)?;
+		if !table.complete && rule_id >= table.visible {
+			return None;
+		}
+		table.answers.get(rule_id).cloned()
+	}
+
+	fn add_answer(&mut self, key: &str, answer: Pred) {
+		let table = self.tables.get_mut(key).unwrap();
+		if table.keys.insert(answer.to_string()) {
+			table.answers.push(Clause {
+				head: answer,
+				body: Vec::new(),
+			});
+			self.answer_count += 1;
+		}
+	}
+
+	// whether the current answers of key can be used without evaluating it, and
+	// whether they were cut off by dmax
+	fn consume(&mut self, key: &str) -> Option<bool> {
+		let low = match self.tables.get(key) {
+			None => return None,
+			Some(table) if table.complete => return Some(table.truncated),
+			Some(table) => match table.stack_index {
+				Some(index) => index,
+				// evaluated in this pass, its scc leader is still running
+				None if table.pass == self.pass => table.low,
+				// evaluated in an earlier pass, recompute
+				None => return None,
+			},
+		};
+		let top = self.stack.last().unwrap();
+		let table = self.tables.get_mut(top).unwrap();
+		table.low = table.low.min(low);
+		Some(false)
+	}
+
+	fn push(&mut self, key: &str) {
+		let index = self.stack.len();
+		self.stack.push(key.to_string());
+		let table = self.tables.entry(key.to_string()).or_default();
+		table.stack_index = Some(index);
+		table.low = index;
+		table.pass = self.pass;
+	}
+
+	fn pop(&mut self) {
+		let key = self.stack.pop().unwrap();
+		let index = self.stack.len();
+		let table = self.tables.get_mut(&key).unwrap();
+		table.stack_index = None;
+		let low = table.low;
+		let truncated = table.truncated;
+		let mut members = std::mem::take(&mut table.members);
+		if low >= index {
+			table.complete = true;
+			for member in members.into_iter() {
+				let member = self.tables.get_mut(&member).unwrap();
+				member.complete = true;
+				member.truncated |= truncated;
+			}
+		} else {
+			// depends on an outer call, completed by the leader of the scc
+			members.push(key);
+			let parent = self.tables.get_mut(self.stack.last().unwrap()).unwrap();
+			parent.low = parent.low.min(low);
+			parent.members.extend(members);
+		}
+	}
+
+	// fixpoint iteration of call, returns whether some branch exceeded dmax, at
+	// most dmax passes are made as a call may have infinitely many answers
+	pub fn evaluate(
+		theory: &Theory,
+		tables: &Rc<RefCell<Tables>>,
+		call: &Pred,
+		key: &str,
+		dmax: usize,
+	) -> Result<bool, ProveError> {
+		if let Some(depth_flag) = tables.borrow_mut().consume(key) {
+			return Ok(depth_flag);
+		}
+		tables.borrow_mut().push(key);
+		let index = tables.borrow().stack.len() - 1;
+		let mut depth_flag = false;
+		for passes in 1.. {
+			let count = tables.borrow().answer_count;
+			{
+				let mut tables = tables.borrow_mut();
+				let table = tables.tables.get_mut(key).unwrap();
+				table.visible = table.answers.len();
+			}
+			let targets: VecDeque<Pred> = std::iter::once(call.clone()).collect();
+			let mut solutions = Solutions::new_table(theory, targets, tables.clone(), dmax);
+			for answer in solutions.by_ref() {
+				// answers are by name, _id for the variables of the canonical call
+				let instmap: InstMap = call
+					.variables()
+					.into_iter()
+					.map(|var| (var, answer[&var.to_string()].clone()))
+					.collect();
+				let answer = call.instantiate(&instmap).unwrap().canonical();
+				tables.borrow_mut().add_answer(key, answer);
+			}
+			if let Some(error) = solutions.error() {
+				// the query is aborted, the tables are dropped with it
+				let error = error.clone();
+				tables.borrow_mut().pop();
+				return Err(error);
+			}
+			depth_flag |= solutions.depth_exceeded();
+			let mut tables = tables.borrow_mut();
+			// only the leader of an scc iterates to the fixpoint
+			if tables.answer_count == count || tables.tables[key].low < index {
+				break;
+			}
+			if passes >= dmax {
+				tables.tables.get_mut(key).unwrap().truncated = true;
+				depth_flag = true;
+				break;
+			}
+			tables.pass += 1;
+		}
+		tables.borrow_mut().pop();
+		Ok(depth_flag)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::error::ParseErrorKind;
+	use crate::theory::{ProveResult, Theory};
+
+	#[test]
+	fn table_left_recursion() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				":- table greater/2.
+			greater(two, one).
+			greater(three, two).
+			greater(four, three).
+			greater(A, C) :- greater(A, B), greater(B, C).
+			",
+			)
+			.unwrap();
+		assert!(matches!(
+			theory.query("greater(four, one)", 32).unwrap().prove(),
+			ProveResult::Succeed(_)
+		));
+		assert_eq!(
+			theory.query("greater(one, three)", 32).unwrap().prove(),
+			ProveResult::Fail
+		);
+		let mut answers: Vec<String> = theory
+			.query("greater(X, one)", 32)
+			.unwrap()
+			.map(|answer| answer["X"].to_string())
+			.collect();
+		answers.sort();
+		assert_eq!(answers, vec!["four", "three", "two"]);
+		let errors = theory
+			.add_string(":- table greater. :- dynamic greater/2.")
+			.unwrap_err();
+		assert!(errors
+			.iter()
+			.all(|error| error.kind == ParseErrorKind::InvalidDirective));
+	}
+
+	#[test]
+	fn table_equivalence() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"eq(X, Y) :- eq(Y, X).
+			eq(X, Y) :- eq(X, Z), eq(Z, Y).
+			eq(X, X) :- elem(X).
+			eq(a, b).
+			eq(c, b).
+			eq(d, e).
+			elem(a).
+			elem(b).
+			elem(c).
+			elem(d).
+			elem(e).
+			",
+			)
+			.unwrap();
+		theory.set_tabled("eq", 2);
+		assert!(matches!(
+			theory.query("eq(a, c)", 32).unwrap().prove(),
+			ProveResult::Succeed(_)
+		));
+		assert_eq!(
+			theory.query("eq(a, e)", 32).unwrap().prove(),
+			ProveResult::Fail
+		);
+		assert_eq!(theory.query("eq(b, X)", 32).unwrap().count(), 3);
+	}
+
+	#[test]
+	fn table_infinite_answers() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				":- table nat/1.
+			nat(z).
+			nat(s(X)) :- nat(X).
+			",
+			)
+			.unwrap();
+		match theory.query("nat(X)", 32).unwrap().prove() {
+			ProveResult::Succeed(answer) => assert_eq!(answer["X"].to_string(), "z"),
+			result => panic!("{:?}", result),
+		}
+		// the table stops growing after dmax passes
+		let mut solutions = theory.query("nat(X)", 8).unwrap();
+		assert_eq!(solutions.by_ref().count(), 8);
+		assert!(solutions.depth_exceeded());
+		assert!(matches!(
+			theory.query("nat(s(s(z)))", 32).unwrap().prove(),
+			ProveResult::Succeed(_)
+		));
+	}
+}
@@ -1,240 +1,2065 @@
 #[allow(unused_imports)]
 use ntest::timeout;
-use std::collections::{HashMap, VecDeque};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Read;
+use std::rc::Rc;
 
-use crate::clause::Clause;
-use crate::pred::instmap_to_string;
-use crate::pred::{InstMap, Pred};
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::arith::{self, Number};
+use crate::bindings::{Bindings, Mark, OccursCheck};
+use crate::clause::{Clause, DoubleQuotes, Syntax};
+use crate::error::{ParseError, ParseErrorKind, ProveError, ProveErrorKind, ReadError, Span};
+use crate::index::ClauseIndex;
+use crate::operator::Fixity;
+use crate::pred::{InstMap, Pred, Term, Var};
+use crate::proof::{ProofStep, ProofTree};
+use crate::reader::{self, ClauseReader};
+use crate::symbol::{Functor, Symbol};
+use crate::table::Tables;
+use crate::tracer::{NoTracer, Tracer};
 
 #[derive(Clone, Default)]
 pub struct Theory {
-	clauses: HashMap<String, Vec<Clause>>,
-	suffix_alloc_id: u32,
+	// clauses and their index by name and arity
+	clauses: HashMap<Functor, Vec<Clause>>,
+	indexes: HashMap<Functor, ClauseIndex>,
+	// name and arity of predicates evaluated with answer tables
+	tabled: HashSet<Functor>,
+	// documentation comments by name and arity
+	docs: HashMap<Functor, String>,
+	syntax: Syntax,
+	occurs_check: OccursCheck,
+	// names of the bundled libraries added
+	libraries: HashSet<String>,
 }
 
+// bundled libraries by name
+const LIBRARIES: [(&str, &str); 1] = [("lists", include_str!("library/lists.pl"))];
+
+pub type Answer = HashMap<String, Pred>;
+
 #[derive(Debug, PartialEq)]
 pub enum ProveResult {
-	Succeed,
+	Succeed(Answer),
 	Fail,
 	DepthExceed,
+	Error(ProveError),
 }
 
 impl Theory {
 	pub fn display(&self) {
-		for (_, clauses) in self.clauses.iter() {
+		for clauses in self.clauses.values() {
 			for clause in clauses.iter() {
-				println!("{}", clause.to_string());
+				println!("{}", clause.to_string_with(&self.syntax.operators));
+			}
+		}
+	}
+
+	pub fn get_clause(&self, name: &str, arity: usize, rule_id: usize) -> Option<&Clause> {
+		self.clauses
+			.get(&Functor::new(Symbol::intern(name), arity))?
+			.get(rule_id)
+	}
+
+	// rule_id-th clause of the predicate of the root of target
+	fn clause(&self, target: &Pred, rule_id: usize) -> Option<&Clause> {
+		self.clauses.get(&target.root().functor()?)?.get(rule_id)
+	}
+
+	// ids of the clauses that may resolve target, None for all of them
+	fn candidates(&self, target: &Pred) -> Option<Vec<usize>> {
+		self.indexes
+			.get(&target.root().functor()?)?
+			.candidates(target)
+	}
+
+	pub fn set_tabled(&mut self, name: &str, arity: usize) {
+		self.tabled
+			.insert(Functor::new(Symbol::intern(name), arity));
+	}
+
+	// adds the clauses of a bundled library once, read with the default syntax
+	// whatever the operators and flags of the theory, false if there is none of name
+	pub fn use_library(&mut self, name: &str) -> Result<bool, Vec<ParseError>> {
+		let source = match LIBRARIES.iter().find(|(library, _)| *library == name) {
+			None => return Ok(false),
+			Some((_, source)) => source,
+		};
+		if self.libraries.insert(name.to_string()) {
+			let syntax = std::mem::take(&mut self.syntax);
+			let result = self.add_string(source);
+			self.syntax = syntax;
+			result?;
+		}
+		Ok(true)
+	}
+
+	pub fn set_double_quotes(&mut self, double_quotes: DoubleQuotes) {
+		self.syntax.double_quotes = double_quotes;
+	}
+
+	pub fn set_occurs_check(&mut self, occurs_check: OccursCheck) {
+		self.occurs_check = occurs_check;
+	}
+
+	// op/3 for the clauses added afterwards, false if name may not be an operator
+	pub fn op(&mut self, priority: usize, fixity: Fixity, name: &str) -> bool {
+		self.syntax.operators.add(priority, fixity, name)
+	}
+
+	// pred written with the operators of the theory
+	pub fn term_to_string(&self, pred: &Pred) -> String {
+		pred.to_string_with(&self.syntax.operators)
+	}
+
+	// text of the %! and /** */ comments before the clauses of a predicate
+	pub fn documentation(&self, name: &str, arity: usize) -> Option<&str> {
+		self.docs
+			.get(&Functor::new(Symbol::intern(name), arity))
+			.map(|doc| doc.as_str())
+	}
+
+	fn is_tabled(&self, pred: &Pred) -> bool {
+		match pred.root().functor() {
+			None => false,
+			Some(functor) => self.tabled.contains(&functor),
+		}
+	}
+
+	// `:- table name/arity, ...`, `:- set_prolog_flag(Flag, Value)` for double_quotes
+	// and occurs_check, `:- use_module(library(Name))` and `:- op(Priority, Type, Names)`
+	fn add_directive(&mut self, string: &str) -> Result<(), ParseErrorKind> {
+		// read as one term, the commas of a table directive do not separate goals
+		let (goals, _) =
+			Clause::goals_from_string_with(&format!("({}\n)", string), 0, &self.syntax)
+				.map_err(|_| ParseErrorKind::InvalidDirective)?;
+		let directive = match goals.as_slice() {
+			[goal] if goal.get_name() == ":-" && goal.root().arity() == 1 => goal.args().remove(0),
+			_ => return Err(ParseErrorKind::InvalidDirective),
+		};
+		let args = directive.args();
+		let atom = |pred: &Pred| match pred.root() {
+			Term::Atom(name) => Some(name.name()),
+			_ => None,
+		};
+		match (directive.get_name().as_str(), args.as_slice()) {
+			("op", [_, _, _]) => self.op_directive(&args),
+			("table", [specs]) => self.table_directive(specs),
+			("use_module", [library]) if library.get_name() == "library" => {
+				let name = match library.args().as_slice() {
+					[name] => atom(name),
+					_ => None,
+				};
+				match name.map(|name| self.use_library(name)) {
+					Some(Ok(true)) => Ok(()),
+					Some(Err(errors)) => Err(errors[0].kind),
+					_ => Err(ParseErrorKind::InvalidDirective),
+				}
+			}
+			("set_prolog_flag", [flag, value]) => {
+				match (atom(flag), atom(value)) {
+					(Some("double_quotes"), Some(value)) => self.set_double_quotes(match value {
+						"codes" => DoubleQuotes::Codes,
+						"chars" => DoubleQuotes::Chars,
+						"string" => DoubleQuotes::String,
+						_ => return Err(ParseErrorKind::InvalidDirective),
+					}),
+					(Some("occurs_check"), Some(value)) => self.set_occurs_check(match value {
+						"false" => OccursCheck::False,
+						"true" => OccursCheck::True,
+						"error" => OccursCheck::Error,
+						_ => return Err(ParseErrorKind::InvalidDirective),
+					}),
+					_ => return Err(ParseErrorKind::InvalidDirective),
+				}
+				Ok(())
+			}
+			_ => Err(ParseErrorKind::InvalidDirective),
+		}
+	}
+
+	// name/arity specs separated by commas, none is tabled if one is malformed
+	fn table_directive(&mut self, specs: &Pred) -> Result<(), ParseErrorKind> {
+		let mut pending = vec![specs.clone()];
+		let mut tabled = Vec::new();
+		while let Some(spec) = pending.pop() {
+			let args = spec.args();
+			match (spec.get_name().as_str(), args.as_slice()) {
+				(",", [first, rest]) => {
+					pending.push(rest.clone());
+					pending.push(first.clone());
+				}
+				("/", [name, arity]) => {
+					let arity = arity.root().get_integer().and_then(|x| x.to_usize());
+					match (name.root(), arity) {
+						(Term::Atom(name), Some(arity)) => tabled.push(Functor::new(*name, arity)),
+						_ => return Err(ParseErrorKind::InvalidDirective),
+					}
+				}
+				_ => return Err(ParseErrorKind::InvalidDirective),
+			}
+		}
+		self.tabled.extend(tabled);
+		Ok(())
+	}
+
+	// names is an atom or a list of atoms, none is defined if one may not be
+	fn op_directive(&mut self, args: &[Pred]) -> Result<(), ParseErrorKind> {
+		let priority = args[0]
+			.root()
+			.get_integer()
+			.and_then(|priority| priority.to_usize());
+		let fixity = match args[1].root() {
+			Term::Atom(name) => Fixity::from_name(name.name()),
+			_ => None,
+		};
+		let names = args[2]
+			.list_items()
+			.unwrap_or_else(|| vec![args[2].clone()]);
+		let (priority, fixity) = match (priority, fixity) {
+			(Some(priority), Some(fixity)) => (priority, fixity),
+			_ => return Err(ParseErrorKind::InvalidDirective),
+		};
+		let mut operators = self.syntax.operators.clone();
+		for name in names.iter() {
+			let added = match name.root() {
+				Term::Atom(name) => operators.add(priority, fixity, name.name()),
+				_ => false,
+			};
+			if !added {
+				return Err(ParseErrorKind::InvalidDirective);
 			}
 		}
+		self.syntax.operators = operators;
+		Ok(())
 	}
 
-	pub fn add_string(&mut self, string: &str) {
-		let mut strings = string
-			.split('.')
-			.map(|x| x.to_string())
-			.collect::<Vec<String>>();
-		strings.pop();
-		for clause in strings.into_iter() {
-			if clause.is_empty() {
+	// clauses that parse are added even if others fail, all errors are returned
+	pub fn add_string(&mut self, string: &str) -> Result<(), Vec<ParseError>> {
+		self.add_reader(string.as_bytes())
+			.map_err(|error| match error {
+				ReadError::Parse(errors) => errors,
+				ReadError::Io(_) => vec![ParseError {
+					kind: ParseErrorKind::Unreadable,
+					clause: String::new(),
+					span: Span::new(string, string.len(), string.len()),
+				}],
+			})
+	}
+
+	// reads clauses until the end of input, stops at the first io error
+	pub fn add_reader<R: Read>(&mut self, input: R) -> Result<(), ReadError> {
+		let mut errors = Vec::new();
+		for clause in ClauseReader::new(input) {
+			let (clause, span) = match clause {
+				Ok(clause) => clause,
+				Err(ReadError::Parse(parse_errors)) => {
+					errors.extend(parse_errors);
+					continue;
+				}
+				Err(error) => return Err(error),
+			};
+			let clause = clause.as_str();
+			let code = reader::skip_layout(clause);
+			if code.is_empty() {
 				continue;
 			}
-			let (new_clause, new_id) = Clause::from_string(&clause, self.suffix_alloc_id);
-			self.suffix_alloc_id = new_id;
-			let name = new_clause.get_name();
-			match self.clauses.get(&name) {
-				None => {
-					self.clauses.insert(new_clause.get_name(), vec![new_clause]);
+			if code.starts_with(":-") {
+				if let Err(kind) = self.add_directive(code) {
+					errors.push(ParseError {
+						kind,
+						clause: clause.to_string(),
+						span,
+					});
 				}
-				_ => {
-					let mut poped = self.clauses.remove(&name).unwrap();
-					poped.push(new_clause);
-					self.clauses.insert(name, poped);
+				continue;
+			}
+			let new_clause = match Clause::from_string_with(clause, 0, &self.syntax) {
+				Ok((new_clause, _)) => new_clause,
+				Err(mut error) => {
+					error.span = error.span.within(span);
+					errors.push(error);
+					continue;
+				}
+			};
+			if let Some(doc) = reader::doc_comment(clause) {
+				let functor = new_clause.head.root().functor().unwrap();
+				let entry = self.docs.entry(functor).or_default();
+				if !entry.is_empty() {
+					entry.push_str("\n\n");
 				}
+				entry.push_str(&doc);
 			}
+			let functor = new_clause.head.root().functor().unwrap();
+			self.indexes
+				.entry(functor)
+				.or_default()
+				.push(&new_clause.head);
+			self.clauses.entry(functor).or_default().push(new_clause);
+		}
+		if errors.is_empty() {
+			Ok(())
+		} else {
+			Err(ReadError::Parse(errors))
 		}
 	}
 
 	pub fn prove(&self, dmax: usize) -> ProveResult {
-		#[inline]
-		fn update_targets(targets: &mut VecDeque<Pred>, instmap: &InstMap) -> bool {
-			for target in targets.iter_mut() {
-				match target.instantiate(&instmap) {
-					Some(target_insted) => *target = target_insted,
-					None => return false,
+		self.prove_with_tracer(dmax, &mut NoTracer)
+	}
+
+	pub fn prove_with_tracer(&self, dmax: usize, tracer: &mut dyn Tracer) -> ProveResult {
+		let goals = match self.clauses.get(&Functor::new(Symbol::intern("goal"), 0)) {
+			None => return ProveResult::Fail,
+			Some(goals) => goals,
+		};
+		let mut depth_flag = false;
+		for goal in goals.iter() {
+			match self
+				.query_preds(goal.body.iter().cloned(), dmax)
+				.with_tracer(&mut *tracer)
+				.prove()
+			{
+				ProveResult::Fail => {}
+				ProveResult::DepthExceed => depth_flag = true,
+				result => return result,
+			}
+		}
+		if depth_flag {
+			ProveResult::DepthExceed
+		} else {
+			ProveResult::Fail
+		}
+	}
+
+	// answers of every goal() clause, in clause order
+	pub fn solutions(&self, dmax: usize) -> impl Iterator<Item = Answer> + '_ {
+		self.clauses
+			.get(&Functor::new(Symbol::intern("goal"), 0))
+			.into_iter()
+			.flatten()
+			.flat_map(move |goal| self.query_preds(goal.body.iter().cloned(), dmax))
+	}
+
+	// conjunction of goals in clause body syntax, e.g. "parent(X, b), male(X)"
+	pub fn query(&self, string: &str, dmax: usize) -> Result<Solutions<'_>, ParseError> {
+		let (goals, new_id) = Clause::goals_from_string_with(string, 0, &self.syntax)?;
+		Ok(Solutions::new(
+			self,
+			goals.into_iter().collect(),
+			new_id,
+			dmax,
+		))
+	}
+
+	pub fn query_preds<I: IntoIterator<Item = Pred>>(
+		&self,
+		goals: I,
+		dmax: usize,
+	) -> Solutions<'_> {
+		Solutions::new(self, goals.into_iter().collect(), 0, dmax)
+	}
+}
+
+// depth first search over the SLD tree, each next() resumes from the last choice point
+pub struct Solutions<'a> {
+	theory: &'a Theory,
+	dmax: usize,
+	variables: Vec<Var>,
+	// the goal of the top frame with its bindings substituted
+	target: Pred,
+	// roots of the goals of each frame in bindings, the goal resolved first in front
+	targets_stack: Vec<VecDeque<usize>>,
+	id_stack: Vec<u32>,
+	rule_id_stack: Vec<usize>,
+	// clause ids from the index for the target of each frame, rule_id is a position in it
+	candidates_stack: Vec<Option<Vec<usize>>>,
+	// the goals and bindings of the whole branch
+	bindings: Bindings,
+	// state of bindings before each alternative of the frame, undone to on retry
+	mark_stack: Vec<Mark>,
+	// alternatives of the frame were removed by a cut
+	cut_stack: Vec<bool>,
+	depth_flag: bool,
+	// an answer was yielded, backtrack before searching again
+	resume: bool,
+	// the query holds no provable target, its only answer is yielded once
+	trivial: bool,
+	tracer: Box<dyn Tracer + 'a>,
+	tables: Rc<RefCell<Tables>>,
+	// evaluating a tabled call, its root is resolved by clauses not by the table
+	table_root: bool,
+	error: Option<ProveError>,
+}
+
+impl<'a> Solutions<'a> {
+	// the variables of renamed clauses are numbered from suffix_alloc_id on, or
+	// above the variables of the targets if they reach it
+	fn new(
+		theory: &'a Theory,
+		mut targets: VecDeque<Pred>,
+		mut suffix_alloc_id: u32,
+		dmax: usize,
+	) -> Solutions<'a> {
+		let mut variables: Vec<Var> = Vec::new();
+		for each_target in targets.iter() {
+			for var in each_target.variables().into_iter() {
+				suffix_alloc_id = suffix_alloc_id.max(var.id + 1);
+				let named = var.name.is_some_and(|name| !name.name().starts_with('_'));
+				if named && !variables.contains(&var) {
+					variables.push(var);
 				}
 			}
-			true
 		}
+		// a cut in the query removes every choice point of the query
+		set_cut_barrier(&mut targets, 0);
+		let mut bindings = Bindings::new(theory.occurs_check);
+		let mut roots = targets.iter().map(|x| bindings.add(x)).collect();
+		let (target, trivial) = match first_available_target(&mut roots, &mut bindings) {
+			None => (Default::default(), true),
+			Some(target) => (target, false),
+		};
+		let candidates = if trivial {
+			None
+		} else {
+			theory.candidates(&target)
+		};
+		Solutions {
+			theory,
+			dmax,
+			variables,
+			target,
+			targets_stack: vec![roots],
+			id_stack: vec![suffix_alloc_id],
+			rule_id_stack: vec![0],
+			candidates_stack: vec![candidates],
+			mark_stack: vec![bindings.mark()],
+			bindings,
+			cut_stack: vec![false],
+			depth_flag: false,
+			resume: false,
+			trivial,
+			tracer: Box::new(NoTracer),
+			tables: Default::default(),
+			table_root: false,
+			error: None,
+		}
+	}
+
+	pub(crate) fn new_table(
+		theory: &'a Theory,
+		targets: VecDeque<Pred>,
+		tables: Rc<RefCell<Tables>>,
+		dmax: usize,
+	) -> Solutions<'a> {
+		// the canonical call has no named variables, every one is answered
+		let variables = targets.iter().flat_map(|x| x.variables()).collect();
+		let mut solutions = Solutions::new(theory, targets, 0, dmax);
+		solutions.variables = variables;
+		solutions.tables = tables;
+		solutions.table_root = true;
+		solutions
+	}
+
+	// rule_id-th clause for the target, answers of the call variant when tabled
+	fn get_clause(&mut self, rule_id: usize) -> Option<Cow<'a, Clause>> {
+		if !self.theory.is_tabled(&self.target)
+			|| (self.table_root && self.targets_stack.len() == 1)
+		{
+			let clause_id = match self.candidates_stack.last().unwrap() {
+				None => rule_id,
+				Some(candidates) => *candidates.get(rule_id)?,
+			};
+			return self
+				.theory
+				.clause(&self.target, clause_id)
+				.map(Cow::Borrowed);
+		}
+		let call = self.target.canonical();
+		let key = call.to_string();
+		if rule_id == 0 {
+			match Tables::evaluate(self.theory, &self.tables, &call, &key, self.dmax) {
+				Ok(depth_flag) => self.depth_flag |= depth_flag,
+				Err(error) => {
+					self.error = Some(error);
+					return None;
+				}
+			}
+		}
+		self.tables.borrow().answer(&key, rule_id).map(Cow::Owned)
+	}
+
+	pub fn with_tracer<T: Tracer + 'a>(mut self, tracer: T) -> Solutions<'a> {
+		self.tracer = Box::new(tracer);
+		self
+	}
+
+	// whether some branch was cut off by dmax so far
+	pub fn depth_exceeded(&self) -> bool {
+		self.depth_flag
+	}
+
+	// error that aborted the search
+	pub fn error(&self) -> Option<&ProveError> {
+		self.error.as_ref()
+	}
+
+	// proof of the answer last returned by next(), one tree per query goal
+	pub fn proof(&mut self) -> Vec<ProofTree> {
+		if !self.resume {
+			return Vec::new();
+		}
+		let mut steps = Vec::new();
+		for i in 0..self.targets_stack.len() {
+			let goal = self.bindings.resolve(self.targets_stack[i][0]);
+			let end = match self.mark_stack.get(i + 1) {
+				Some(mark) => *mark,
+				None => self.bindings.mark(),
+			};
+			let instmap = self.bindings.bound_between(self.mark_stack[i], end);
+			let mut rule_id = self.rule_id_stack[i];
+			// built-ins hold without a proof, the goals a control construct
+			// leads to are subproofs of the parent
+			let transparent = is_builtin(&goal);
+			let arity = if is_control(&goal) {
+				match control_goals(&goal, rule_id, i) {
+					None => 0,
+					Some(goals) => goals.iter().filter(|x| !is_neq(x)).count(),
+				}
+			// answers from a table are facts, rule_id is their position in the table
+			} else if transparent || self.theory.is_tabled(&goal) {
+				0
+			} else {
+				if let Some(candidates) = &self.candidates_stack[i] {
+					rule_id = candidates[rule_id];
+				}
+				self.theory
+					.clause(&goal, rule_id)
+					.unwrap()
+					.body
+					.iter()
+					.filter(|x| !is_neq(x))
+					.count()
+			};
+			steps.push(ProofStep {
+				goal,
+				rule_id,
+				instmap,
+				arity,
+				transparent,
+			});
+		}
+		ProofTree::build_forest(steps)
+	}
 
-		fn first_available_target(targets: &mut VecDeque<Pred>) -> Option<Pred> {
-			let mut i = 0;
-			if targets.is_empty() {
+	// goals replacing a built-in target, its bindings and the next variable id,
+	// None when it fails
+	fn builtin(&mut self, rule_id: usize) -> Option<(VecDeque<Pred>, InstMap, u32)> {
+		let frame = self.targets_stack.len() - 1;
+		let id = *self.id_stack.last().unwrap();
+		if is_control(&self.target) {
+			return control_goals(&self.target, rule_id, frame)
+				.map(|goals| (goals, InstMap::new(), id));
+		}
+		if rule_id > 0 {
+			return None;
+		}
+		if is_arithmetic(&self.target) {
+			return self
+				.arithmetic()
+				.map(|instmap| (VecDeque::new(), instmap, id));
+		}
+		if is_term_comparison(&self.target) {
+			return term_comparison(&self.target).map(|instmap| (VecDeque::new(), instmap, id));
+		}
+		if is_unification(&self.target) {
+			return self
+				.unification()
+				.map(|instmap| (VecDeque::new(), instmap, id));
+		}
+		if is_type_check(&self.target) {
+			return type_check(&self.target).map(|instmap| (VecDeque::new(), instmap, id));
+		}
+		if is_sort(&self.target) {
+			return self.sort().map(|instmap| (VecDeque::new(), instmap, id));
+		}
+		if is_negation(&self.target) {
+			if !self.negation_holds() {
 				return None;
 			}
-			loop {
-				let try_target = targets.pop_front().unwrap();
-				if try_target.nodes.last().unwrap().ident != "Neq" {
-					targets.push_front(try_target.clone());
-					break Some(try_target);
+		} else if is_cut(&self.target) {
+			// frames from the parent call on keep no alternatives
+			for cut in self.cut_stack[cut_barrier(&self.target)..].iter_mut() {
+				*cut = true;
+			}
+		} else if self.target.root().functor() == Some(Functor::SOFT_CUT) {
+			// the else branch is dropped, the condition keeps its alternatives
+			self.cut_stack[cut_barrier(&self.target)] = true;
+		}
+		Some((VecDeque::new(), InstMap::new(), id))
+	}
+
+	// is/2 and the comparisons, an error aborts the query
+	fn arithmetic(&mut self) -> Option<InstMap> {
+		let args = self.target.args();
+		let result = if self.target.root().functor() == Some(Functor::IS) {
+			arith::evaluate(&args[1]).map(|value| {
+				let value = Pred::leaf(Term::Number(value));
+				if let Some(var) = args[0].root().get_var() {
+					Some(std::iter::once((var, value)).collect())
+				} else if args[0] == value {
+					Some(InstMap::new())
+				} else {
+					None
 				}
-				targets.push_back(try_target);
-				i += 1;
-				if i == targets.len() {
-					return None;
+			})
+		} else {
+			arith::compare(&self.target.get_name(), &args[0], &args[1]).map(|holds| {
+				if holds {
+					Some(InstMap::new())
+				} else {
+					None
 				}
+			})
+		};
+		match result {
+			Ok(instmap) => instmap,
+			Err((kind, culprit)) => {
+				self.error = Some(ProveError {
+					kind,
+					goal: self.target.clone(),
+					culprit,
+				});
+				None
+			}
+		}
+	}
+
+	// msort/2 and sort/2 in the standard order, sort/2 drops duplicates
+	fn sort(&mut self) -> Option<InstMap> {
+		let args = self.target.args();
+		let mut items = match args[0].list_items() {
+			Some(items) => items,
+			None => {
+				self.error = Some(ProveError {
+					kind: ProveErrorKind::Instantiation,
+					goal: self.target.clone(),
+					culprit: args[0].clone(),
+				});
+				return None;
 			}
+		};
+		items.sort_by(|a, b| a.standard_cmp(b));
+		if self.target.root().functor() == Some(Functor::SORT) {
+			items.dedup_by(|a, b| a.standard_cmp(b) == Ordering::Equal);
 		}
+		self.unify(&args[1], &Pred::list(items), self.theory.occurs_check)
+	}
 
-		let mut target = Pred::vc_from_string("goal".to_string());
-		let mut targets_stack: Vec<VecDeque<Pred>> =
-			vec![std::iter::once(target.clone()).collect()];
-		let mut id_stack: Vec<u32> = vec![self.suffix_alloc_id];
-		let mut rule_id_stack: Vec<usize> = vec![0];
-		let mut depth_flag = false;
+	// =/2 binds the variables of both sides, \=/2 holds if they do not unify,
+	// unify_with_occurs_check/2 checks whatever the flag of the theory
+	fn unification(&mut self) -> Option<InstMap> {
+		let args = self.target.args();
+		let functor = self.target.root().functor().unwrap();
+		let occurs_check = if functor == Functor::UNIFY_WITH_OCCURS_CHECK {
+			OccursCheck::True
+		} else {
+			self.theory.occurs_check
+		};
+		let unifier = self.unify(&args[0], &args[1], occurs_check);
+		if functor != Functor::NOT_UNIFY {
+			unifier
+		} else if unifier.is_none() {
+			Some(InstMap::new())
+		} else {
+			None
+		}
+	}
+
+	// unifier of a and b, an occurs check error aborts the query
+	fn unify(&mut self, a: &Pred, b: &Pred, occurs_check: OccursCheck) -> Option<InstMap> {
+		match a.unify_with(b, occurs_check) {
+			Ok(unifier) => unifier,
+			Err(culprit) => {
+				self.error = Some(ProveError {
+					kind: ProveErrorKind::OccursCheck,
+					goal: self.target.clone(),
+					culprit,
+				});
+				None
+			}
+		}
+	}
+
+	// the argument of the negation target has no proof
+	fn negation_holds(&mut self) -> bool {
+		let goal = self.target.args().remove(0);
+		if self.flounders() {
+			self.error = Some(ProveError {
+				kind: ProveErrorKind::Instantiation,
+				goal: self.target.clone(),
+				culprit: goal,
+			});
+			return false;
+		}
+		let mut solutions = Solutions::new(
+			self.theory,
+			std::iter::once(goal).collect(),
+			*self.id_stack.last().unwrap(),
+			self.dmax,
+		);
+		solutions.tables = self.tables.clone();
+		let answer = solutions.next();
+		if let Some(error) = solutions.error {
+			self.error = Some(error);
+			return false;
+		}
+		if answer.is_some() {
+			return false;
+		}
+		if solutions.depth_exceeded() {
+			// unknown rather than proven false
+			self.depth_flag = true;
+			return false;
+		}
+		true
+	}
+
+	// a non-ground negation only runs once nothing else is left, its failure
+	// says nothing about the bindings of the answer variables it holds
+	fn flounders(&mut self) -> bool {
+		let variables = self.target.variables();
+		if variables.is_empty() {
+			return false;
+		}
+		self.answer()
+			.values()
+			.any(|value| value.variables().iter().any(|x| variables.contains(x)))
+	}
+
+	// first answer only
+	pub fn prove(mut self) -> ProveResult {
+		match self.next() {
+			Some(answer) => ProveResult::Succeed(answer),
+			None => match self.error {
+				Some(error) => ProveResult::Error(error),
+				None if self.depth_flag => ProveResult::DepthExceed,
+				None => ProveResult::Fail,
+			},
+		}
+	}
+
+	// binds the variables of instmap in bindings, false if some binding fails
+	fn bind(&mut self, instmap: &InstMap) -> bool {
+		for (var, value) in instmap.iter() {
+			let cell = match self.bindings.var(*var) {
+				Some(cell) => cell,
+				None => return false,
+			};
+			let value = self.bindings.add(value);
+			if self.bindings.unify(cell, value) != Ok(true) {
+				return false;
+			}
+		}
+		true
+	}
+
+	// the query variables with the bindings of the branch substituted
+	fn answer(&mut self) -> Answer {
+		let mut answer: Answer = HashMap::new();
+		for var in self.variables.iter() {
+			let cell = self.bindings.var(*var).unwrap();
+			answer.insert(var.to_string(), self.bindings.resolve(cell));
+		}
+		answer
+	}
+
+	fn backtrack(&mut self) -> bool {
+		// current stack element fail
+		self.targets_stack.pop().unwrap();
+		self.candidates_stack.pop();
+		self.cut_stack.pop();
+		self.mark_stack.pop();
+		let root = match self.targets_stack.last() {
+			None => return false,
+			Some(targets) => targets[0],
+		};
+		*self.rule_id_stack.last_mut().unwrap() += 1;
+		self.id_stack.pop();
+		self.bindings.undo(*self.mark_stack.last().unwrap());
+		self.target = self.bindings.resolve(root);
+		true
+	}
+}
+
+impl<'a> Iterator for Solutions<'a> {
+	type Item = Answer;
+
+	fn next(&mut self) -> Option<Answer> {
+		if self.targets_stack.is_empty() {
+			return None;
+		}
+		if self.trivial {
+			self.targets_stack.clear();
+			let answer = self.answer();
+			self.tracer.succeed(&answer);
+			return Some(answer);
+		}
+		if self.resume {
+			self.resume = false;
+			*self.rule_id_stack.last_mut().unwrap() += 1;
+			self.id_stack.pop();
+		}
 		loop {
-			//println!("ris {:?}", rule_id_stack);
-			let mut rule_id = rule_id_stack.pop().unwrap();
-			if targets_stack.len() > dmax {
-				println!("[31mDEEP[0m");
-				depth_flag = true;
-			} else if let Some(vec_clause) = self.clauses.get(&target.get_name()) {
-				let id = id_stack.last().unwrap();
-				if rule_id < vec_clause.len() {
-					match vec_clause[rule_id].match_target(target.clone(), *id) {
-						None => {
-							rule_id += 1;
-							rule_id_stack.push(rule_id);
-							continue;
-						}
-						Some((mut new_targets, instmap, new_id)) => {
-							println!(
-								"[32mMATCH[0m {} [32mto[0m {}",
-								target.to_string(),
-								vec_clause[rule_id],
-							);
-							println!("[32mwith[0m {}",
-								instmap_to_string(&instmap)
-							);
-							let mut targets_copy = targets_stack.last().unwrap().clone();
-							targets_copy.pop_front().unwrap();
-							if update_targets(&mut targets_copy, &instmap) {
-								rule_id_stack.push(rule_id);
-								id_stack.push(new_id);
-								new_targets.extend(targets_copy);
-								target = match first_available_target(&mut new_targets) {
-									None => {
-										println!("[36mCLEAR[0m");
-										return ProveResult::Succeed;
-									}
-									Some(target) => target,
-								};
-								targets_stack.push(new_targets);
-								rule_id_stack.push(0);
-								continue;
-							}
+			let rule_id = self.rule_id_stack.pop().unwrap();
+			let mark = *self.mark_stack.last().unwrap();
+			self.bindings.undo(mark);
+			if rule_id == 0 && self.tracer.enabled() {
+				let bindings = &mut self.bindings;
+				let targets: VecDeque<Pred> = self
+					.targets_stack
+					.last()
+					.unwrap()
+					.iter()
+					.map(|x| bindings.resolve(*x))
+					.collect();
+				self.tracer.call(&self.target, &targets);
+			}
+			let step = if rule_id > 0 && *self.cut_stack.last().unwrap() {
+				None
+			} else if self.targets_stack.len() > self.dmax {
+				self.tracer.depth_exceed(&self.target);
+				self.depth_flag = true;
+				None
+			} else if is_builtin(&self.target) {
+				self.builtin(rule_id)
+					.filter(|(_, instmap, _)| self.bind(instmap))
+					.map(|(goals, _, id)| (goals, id))
+			} else if let Some(clause) = self.get_clause(rule_id) {
+				let id = *self.id_stack.last().unwrap();
+				let root = self.targets_stack.last().unwrap()[0];
+				match clause.resolve(&mut self.bindings, root, id) {
+					Ok(None) => {
+						self.rule_id_stack.push(rule_id + 1);
+						continue;
+					}
+					Ok(Some((mut goals, id))) => {
+						if self.tracer.enabled() {
+							let end = self.bindings.mark();
+							let instmap = self.bindings.bound_between(mark, end);
+							self.tracer.matched(&self.target, &clause, &instmap);
 						}
+						set_cut_barrier(&mut goals, self.targets_stack.len() - 1);
+						Some((goals, id))
+					}
+					Err(culprit) => {
+						self.error = Some(ProveError {
+							kind: ProveErrorKind::OccursCheck,
+							goal: self.target.clone(),
+							culprit,
+						});
+						None
 					}
 				}
+			} else {
+				None
+			};
+			if let Some(error) = &self.error {
+				self.tracer.error(error);
+				self.targets_stack.clear();
+				return None;
 			}
-			println!("[31mFAIL[0m");
-			// current stack element fail
-			targets_stack.pop().unwrap();
-			// println!("{} {}", targets_stack.len(), rule_id_stack.len());
-			match targets_stack.last() {
-				None => {
-					if depth_flag {
-						return ProveResult::DepthExceed;
-					} else {
-						return ProveResult::Fail;
+			if let Some((goals, new_id)) = step {
+				let mut targets: VecDeque<usize> =
+					goals.iter().map(|x| self.bindings.add(x)).collect();
+				targets.extend(self.targets_stack.last().unwrap().iter().skip(1));
+				if violates_neq(&targets, &mut self.bindings) {
+					// try next rule
+					self.rule_id_stack.push(rule_id + 1);
+					continue;
+				}
+				self.rule_id_stack.push(rule_id);
+				self.id_stack.push(new_id);
+				self.target = match first_available_target(&mut targets, &mut self.bindings) {
+					None => {
+						self.resume = true;
+						let answer = self.answer();
+						self.tracer.succeed(&answer);
+						return Some(answer);
 					}
+					Some(target) => target,
+				};
+				self.targets_stack.push(targets);
+				self.mark_stack.push(self.bindings.mark());
+				self.candidates_stack
+					.push(self.theory.candidates(&self.target));
+				self.cut_stack.push(false);
+				self.rule_id_stack.push(0);
+				continue;
+			}
+			self.tracer.fail(&self.target);
+			if !self.backtrack() {
+				return None;
+			}
+		}
+	}
+}
+
+fn is_negation(pred: &Pred) -> bool {
+	matches!(
+		pred.root(),
+		Term::Compound(Functor::NEGATION | Functor::NOT, _)
+	)
+}
+
+// ! and !(frame)
+fn is_cut(pred: &Pred) -> bool {
+	matches!(
+		pred.root(),
+		Term::Atom(Symbol::CUT) | Term::Compound(Functor::CUT, _)
+	)
+}
+
+fn is_neq(pred: &Pred) -> bool {
+	matches!(pred.root(), Term::Compound(Functor::NEQ, _))
+}
+
+fn is_arithmetic(pred: &Pred) -> bool {
+	matches!(
+		pred.root(),
+		Term::Compound(
+			Functor::IS
+				| Functor::LESS
+				| Functor::LESS_EQUAL
+				| Functor::GREATER
+				| Functor::GREATER_EQUAL
+				| Functor::EQUAL
+				| Functor::NOT_EQUAL,
+			_
+		)
+	)
+}
+
+fn is_term_comparison(pred: &Pred) -> bool {
+	matches!(
+		pred.root(),
+		Term::Compound(
+			Functor::IDENTICAL
+				| Functor::NOT_IDENTICAL
+				| Functor::TERM_LESS
+				| Functor::TERM_LESS_EQUAL
+				| Functor::TERM_GREATER
+				| Functor::TERM_GREATER_EQUAL
+				| Functor::COMPARE,
+			_
+		)
+	)
+}
+
+// ==/2, @</2 and the like in the standard order of terms, compare/3 binds
+// its first argument to <, = or >
+fn term_comparison(target: &Pred) -> Option<InstMap> {
+	let args = target.args();
+	let functor = target.root().functor().unwrap();
+	if functor == Functor::COMPARE {
+		let order = Pred::leaf(Term::Atom(Symbol::intern(
+			match args[1].standard_cmp(&args[2]) {
+				Ordering::Less => "<",
+				Ordering::Equal => "=",
+				Ordering::Greater => ">",
+			},
+		)));
+		return if let Some(var) = args[0].root().get_var() {
+			Some(std::iter::once((var, order)).collect())
+		} else if args[0] == order {
+			Some(InstMap::new())
+		} else {
+			None
+		};
+	}
+	let ordering = args[0].standard_cmp(&args[1]);
+	let holds = match functor {
+		Functor::IDENTICAL => ordering == Ordering::Equal,
+		Functor::NOT_IDENTICAL => ordering != Ordering::Equal,
+		Functor::TERM_LESS => ordering == Ordering::Less,
+		Functor::TERM_LESS_EQUAL => ordering != Ordering::Greater,
+		Functor::TERM_GREATER => ordering == Ordering::Greater,
+		_ => ordering != Ordering::Less,
+	};
+	if holds {
+		Some(InstMap::new())
+	} else {
+		None
+	}
+}
+
+fn is_unification(pred: &Pred) -> bool {
+	matches!(
+		pred.root(),
+		Term::Compound(
+			Functor::UNIFY | Functor::NOT_UNIFY | Functor::UNIFY_WITH_OCCURS_CHECK,
+			_
+		)
+	)
+}
+
+fn is_type_check(pred: &Pred) -> bool {
+	matches!(
+		pred.root(),
+		Term::Compound(
+			Functor::VAR
+				| Functor::NONVAR
+				| Functor::ATOM
+				| Functor::NUMBER
+				| Functor::INTEGER
+				| Functor::FLOAT
+				| Functor::ATOMIC
+				| Functor::COMPOUND
+				| Functor::IS_LIST,
+			_
+		)
+	)
+}
+
+fn type_check(target: &Pred) -> Option<InstMap> {
+	let arg = target.args().remove(0);
+	let node = arg.root();
+	let holds = match target.root().functor().unwrap() {
+		Functor::VAR => node.is_var(),
+		Functor::NONVAR => !node.is_var(),
+		Functor::ATOM => matches!(node, Term::Atom(_)),
+		Functor::NUMBER => matches!(node, Term::Number(_)),
+		Functor::INTEGER => node.get_integer().is_some(),
+		Functor::FLOAT => matches!(node, Term::Number(Number::Float(_))),
+		Functor::ATOMIC => matches!(node, Term::Atom(_) | Term::Number(_) | Term::Str(_)),
+		Functor::COMPOUND => matches!(node, Term::Compound(_, _)),
+		_ => arg.list_items().is_some(),
+	};
+	if holds {
+		Some(InstMap::new())
+	} else {
+		None
+	}
+}
+
+fn is_sort(pred: &Pred) -> bool {
+	matches!(
+		pred.root(),
+		Term::Compound(Functor::MSORT | Functor::SORT, _)
+	)
+}
+
+fn is_control(pred: &Pred) -> bool {
+	matches!(
+		pred.root(),
+		Term::Compound(
+			Functor::AND | Functor::OR | Functor::IF_THEN | Functor::SOFT_IF_THEN,
+			_
+		)
+	)
+}
+
+fn is_arrow(pred: &Pred) -> bool {
+	matches!(
+		pred.root(),
+		Term::Compound(Functor::IF_THEN | Functor::SOFT_IF_THEN, _)
+	)
+}
+
+fn is_builtin(pred: &Pred) -> bool {
+	is_negation(pred)
+		|| is_cut(pred)
+		|| is_control(pred)
+		|| is_arithmetic(pred)
+		|| is_term_comparison(pred)
+		|| is_unification(pred)
+		|| is_type_check(pred)
+		|| is_sort(pred)
+		|| matches!(
+			pred.root(),
+			Term::Compound(Functor::SOFT_CUT, _) | Term::Atom(Symbol::TRUE)
+		)
+}
+
+// functor(frame), cutting back to the frame
+fn cut_to(functor: Functor, frame: usize) -> Pred {
+	let mut result = Pred::leaf(Term::Number(BigInt::from(frame).into()));
+	result.push(Term::Compound(functor, vec![0]));
+	result
+}
+
+// the frame of a cut made by cut_to
+fn cut_barrier(pred: &Pred) -> usize {
+	match pred.nodes[0]
+		.get_integer()
+		.and_then(|frame| frame.to_usize())
+	{
+		Some(frame) => frame,
+		None => unreachable!("{} is not made by cut_to", pred),
+	}
+}
+
+// goals of the rule_id-th alternative of a control construct at frame
+fn control_goals(target: &Pred, rule_id: usize, frame: usize) -> Option<VecDeque<Pred>> {
+	let args = target.args();
+	// every control construct is binary
+	if args.len() != 2 {
+		return None;
+	}
+	let functor = target.root().functor().unwrap();
+	// (arrow, condition) of an if-then-else or soft-cut, and the branch taken first
+	let (condition, then) = if functor == Functor::OR && is_arrow(&args[0]) {
+		let branches = args[0].args();
+		(
+			Some((args[0].root().functor().unwrap(), branches[0].clone())),
+			branches[1].clone(),
+		)
+	} else if is_arrow(target) {
+		(Some((functor, args[0].clone())), args[1].clone())
+	} else {
+		(None, args[0].clone())
+	};
+	let mut goals = VecDeque::new();
+	match (functor, rule_id) {
+		(Functor::AND, 0) => goals.extend(args),
+		(_, 0) => {
+			if let Some((arrow, condition)) = condition {
+				// a cut in the condition is local to it
+				goals.push_back(with_cut_barrier(&condition, frame));
+				goals.push_back(match arrow {
+					Functor::IF_THEN => cut_to(Functor::CUT, frame),
+					_ => cut_to(Functor::SOFT_CUT, frame),
+				});
+			}
+			goals.push_back(then);
+		}
+		(Functor::OR, 1) => goals.push_back(args[1].clone()),
+		_ => return None,
+	}
+	Some(goals)
+}
+
+// bare cuts of a goal become !(frame), control constructs are transparent to cut
+// except for the conditions and negations which are opaque
+fn with_cut_barrier(pred: &Pred, frame: usize) -> Pred {
+	if is_cut(pred) && pred.nodes.len() == 1 {
+		return cut_to(Functor::CUT, frame);
+	}
+	if !is_control(pred) {
+		return pred.clone();
+	}
+	let mut args = pred.args();
+	let transparent = if is_arrow(pred) { 1 } else { 0 };
+	for arg in args[transparent..].iter_mut() {
+		*arg = with_cut_barrier(arg, frame);
+	}
+	Pred::compound(pred.root().functor().unwrap().name(), args)
+}
+
+fn set_cut_barrier(targets: &mut VecDeque<Pred>, frame: usize) {
+	for target in targets.iter_mut() {
+		*target = with_cut_barrier(target, frame);
+	}
+}
+
+// Neq and non-ground negations wait until their variables are bound, a negation
+// that is still non-ground when nothing else is left runs if its variables are
+// local to it, returns the target moved to the front with its bindings substituted
+fn first_available_target(targets: &mut VecDeque<usize>, bindings: &mut Bindings) -> Option<Pred> {
+	let mut negation = None;
+	let mut chosen = None;
+	for (position, root) in targets.iter().enumerate() {
+		match bindings.term(*root) {
+			Term::Compound(Functor::NEQ, _) => continue,
+			Term::Compound(Functor::NEGATION | Functor::NOT, _) => {
+				let target = bindings.resolve(*root);
+				if target.is_ground() {
+					chosen = Some((position, target));
+					break;
+				}
+				if negation.is_none() {
+					negation = Some((position, target));
 				}
-				Some(targets) => target = targets.iter().next().unwrap().clone(),
 			}
-			*rule_id_stack.last_mut().unwrap() += 1;
-			id_stack.pop();
+			_ => {
+				chosen = Some((position, bindings.resolve(*root)));
+				break;
+			}
 		}
 	}
+	let (position, target) = chosen.or(negation)?;
+	let root = targets.remove(position).unwrap();
+	targets.push_front(root);
+	Some(target)
+}
+
+// some Neq goal has the same term on both sides
+fn violates_neq(targets: &VecDeque<usize>, bindings: &mut Bindings) -> bool {
+	targets.iter().any(|root| {
+		matches!(bindings.term(*root), Term::Compound(Functor::NEQ, _))
+			&& bindings.resolve(*root).violates_neq()
+	})
 }
 
 #[cfg(test)]
 mod test {
 	use super::*;
+	use crate::error::{ParseErrorKind, ProveErrorKind};
 
 	#[test]
 	fn simple_prove() {
 		let mut theory: Theory = Default::default();
-		theory.add_string(
-			"parent(X, Y) :- father(X, Y).
+		theory
+			.add_string(
+				"parent(X, Y) :- father(X, Y).
 		father(a, b).
 		goal() :- parent(a, b).",
-		);
+			)
+			.unwrap();
 		match theory.prove(32) {
-			ProveResult::Succeed => {}
+			ProveResult::Succeed(_) => {}
 			_ => panic!("Result not match!"),
 		}
 		let mut theory: Theory = Default::default();
-		theory.add_string(
-			"parent(X, Y) :- father(X, Y).
+		theory
+			.add_string(
+				"parent(X, Y) :- father(X, Y).
 		father(a, b).
 		goal() :- parent(b, a).
 		",
-		);
+			)
+			.unwrap();
 		assert_eq!(theory.prove(32), ProveResult::Fail);
+		// atoms are goals and facts of arity 0
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string("s.\np(X) :- true.\ngoal :- s, p(a).")
+			.unwrap();
+		assert!(matches!(theory.prove(32), ProveResult::Succeed(_)));
+		assert_eq!(theory.query("true", 32).unwrap().count(), 1);
+		assert_eq!(theory.query("s, p(b)", 32).unwrap().count(), 1);
+	}
+
+	#[test]
+	fn prove_arities() {
+		let mut theory: Theory = Default::default();
+		theory.add_string("p(a, b).\np(c).\np(X, d).").unwrap();
+		let mut solutions = theory.query("p(X)", 32).unwrap();
+		assert_eq!(solutions.next().unwrap()["X"].to_string(), "c");
+		// rule_id counts the clauses of p/1 only
+		let proof = solutions.proof();
+		assert_eq!(proof[0].rule_id, 0);
+		assert_eq!(theory.get_clause("p", 1, 0).unwrap().to_string(), "p(c)");
+		assert!(solutions.next().is_none());
+		assert_eq!(theory.query("p(X, Y)", 32).unwrap().count(), 2);
+		assert_eq!(theory.query("p(c, Y)", 32).unwrap().count(), 1);
 	}
 
 	#[test]
 	fn prove_addition() {
 		let mut theory: Theory = Default::default();
-		theory.add_string(
-			"add(s(X), Y, s(Z)) :- add(X, Y, Z).
+		theory
+			.add_string(
+				"add(s(X), Y, s(Z)) :- add(X, Y, Z).
 		add(zero, X, X).
 		goal() :- add(s(s(zero)), s(s(s(zero))), Answer).
 		",
-		);
+			)
+			.unwrap();
 		match theory.prove(32) {
-			ProveResult::Succeed => {}
+			ProveResult::Succeed(_) => {}
 			_ => panic!("Result not match!"),
 		}
 		let mut theory: Theory = Default::default();
-		theory.add_string(
-			"add(s(X), Y, s(Z)) :- add(X, Y, Z).
+		theory
+			.add_string(
+				"add(s(X), Y, s(Z)) :- add(X, Y, Z).
 		add(X, zero, X).
 		goal() :- add(s(s(zero)), s(s(s(zero))), Answer).
 		",
-		);
+			)
+			.unwrap();
 		assert_eq!(theory.prove(32), ProveResult::Fail);
 		let mut theory: Theory = Default::default();
-		theory.add_string(
-			"add(s(X), Y, s(Z)) :- add(X, Y, Z).
+		theory
+			.add_string(
+				"add(s(X), Y, s(Z)) :- add(X, Y, Z).
 		add(zero, X, X).
 		goal() :- add(Answer, s(s(s(zero))), s(s(zero))).
 		",
-		);
+			)
+			.unwrap();
 		assert_eq!(theory.prove(32), ProveResult::Fail);
 	}
 
 	#[test]
 	fn prove_partial_order() {
 		let mut theory: Theory = Default::default();
-		theory.add_string(
-			"greater(two, one).
+		theory
+			.add_string(
+				"greater(two, one).
 		greater(three, two).
 		greater(A, C) :- greater(A, B), greater(B, C).
 		goal() :- greater(three, one).
 		",
-		);
+			)
+			.unwrap();
 		match theory.prove(32) {
-			ProveResult::Succeed => {}
+			ProveResult::Succeed(_) => {}
 			_ => panic!("Result not match!"),
 		}
 		let mut theory: Theory = Default::default();
-		theory.add_string(
-			"greater(two, one).
+		theory
+			.add_string(
+				"greater(two, one).
 		greater(three, two).
 		greater(A, C) :- greater(A, B), greater(B, C).
 		goal() :- greater(one, three).
 		",
-		);
+			)
+			.unwrap();
 		assert_eq!(theory.prove(32), ProveResult::DepthExceed);
 	}
+
+	#[test]
+	fn prove_answer() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"add(s(X), Y, s(Z)) :- add(X, Y, Z).
+		add(zero, X, X).
+		goal() :- add(s(s(zero)), s(s(s(zero))), Answer).
+		",
+			)
+			.unwrap();
+		match theory.prove(32) {
+			ProveResult::Succeed(answer) => {
+				assert_eq!(answer.len(), 1);
+				assert_eq!(
+					answer.get("Answer").unwrap().to_string(),
+					"s(s(s(s(s(zero)))))"
+				);
+			}
+			_ => panic!("Result not match!"),
+		}
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"add(s(X), Y, s(Z)) :- add(X, Y, Z).
+		add(zero, X, X).
+		goal() :- add(X, Y, s(s(zero))), add(Y, Y, s(s(s(s(zero))))).
+		",
+			)
+			.unwrap();
+		match theory.prove(32) {
+			ProveResult::Succeed(answer) => {
+				assert_eq!(answer.get("X").unwrap().to_string(), "zero");
+				assert_eq!(answer.get("Y").unwrap().to_string(), "s(s(zero))");
+			}
+			_ => panic!("Result not match!"),
+		}
+	}
+
+	#[test]
+	fn prove_all_solutions() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"add(s(X), Y, s(Z)) :- add(X, Y, Z).
+		add(zero, X, X).
+		goal() :- add(X, Y, s(s(zero))).
+		",
+			)
+			.unwrap();
+		let answers: Vec<String> = theory
+			.solutions(32)
+			.map(|answer| format!("{} {}", answer["X"], answer["Y"]))
+			.collect();
+		assert_eq!(
+			answers,
+			vec!["s(s(zero)) zero", "s(zero) s(zero)", "zero s(s(zero))"]
+		);
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"parent(a, b).
+		parent(b, c).
+		parent(b, d).
+		ancestor(X, Y) :- parent(X, Y).
+		ancestor(X, Y) :- parent(X, Z), ancestor(Z, Y).
+		goal() :- ancestor(A, d).
+		goal() :- parent(b, C), Neq(C, c).
+		",
+			)
+			.unwrap();
+		let answers: Vec<String> = theory
+			.solutions(32)
+			.map(|answer| answer.values().next().unwrap().to_string())
+			.collect();
+		assert_eq!(answers, vec!["b", "a", "d"]);
+	}
+
+	#[test]
+	fn query_goals() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"add(s(X), Y, s(Z)) :- add(X, Y, Z).
+		add(zero, X, X).
+		",
+			)
+			.unwrap();
+		match theory
+			.query("add(s(zero), s(zero), A), add(A, _, B)", 32)
+			.unwrap()
+			.prove()
+		{
+			ProveResult::Succeed(answer) => {
+				assert_eq!(answer.len(), 2);
+				assert_eq!(answer["A"].to_string(), "s(s(zero))");
+			}
+			_ => panic!("Result not match!"),
+		}
+		assert_eq!(
+			theory.query("add(s(zero), A, zero)", 32).unwrap().prove(),
+			ProveResult::Fail
+		);
+		let (clause, _) = Clause::from_string("add(X, s(zero), s(s(zero)))", 0).unwrap();
+		let answers: Vec<Answer> = theory.query_preds(vec![clause.head], 32).collect();
+		assert_eq!(answers.len(), 1);
+		assert_eq!(answers[0]["X"].to_string(), "s(zero)");
+		// the theory is shared, never cloned
+		std::thread::scope(|scope| {
+			for _ in 0..4 {
+				scope.spawn(|| {
+					assert_eq!(
+						theory.query("add(X, Y, s(s(zero)))", 32).unwrap().count(),
+						3
+					)
+				});
+			}
+		});
+	}
+
+	#[test]
+	fn prove_negation() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"male(tom).
+			male(bob).
+			male(jim).
+			married(tom, ann).
+			bachelor(X) :- male(X), \\+ married(X, _).
+			single(X) :- \\+ married(X, _), male(X).
+			loop(X) :- loop(X).
+			",
+			)
+			.unwrap();
+		let names = |query| {
+			theory
+				.query(query, 32)
+				.unwrap()
+				.map(|answer| answer["X"].to_string())
+				.collect::<Vec<String>>()
+		};
+		assert_eq!(names("bachelor(X)"), vec!["bob", "jim"]);
+		// the non-ground negation waits for male(X)
+		assert_eq!(names("single(X)"), vec!["bob", "jim"]);
+		assert_eq!(names("not(married(X, ann)), male(X)"), vec!["bob", "jim"]);
+		assert_eq!(
+			theory.query("\\+ male(tom)", 32).unwrap().prove(),
+			ProveResult::Fail
+		);
+		assert!(matches!(
+			theory.query("\\+ \\+ male(tom)", 32).unwrap().prove(),
+			ProveResult::Succeed(_)
+		));
+		// left with nothing else, a negation on an answer variable flounders
+		match theory.query("\\+ married(X, Y)", 32).unwrap().prove() {
+			ProveResult::Error(error) => {
+				assert_eq!(error.kind, ProveErrorKind::Instantiation);
+				assert_eq!(error.culprit.to_string(), "married(X, Y)");
+			}
+			result => panic!("{:?}", result),
+		}
+		assert!(matches!(
+			theory.query("\\+ married(_, bob)", 32).unwrap().prove(),
+			ProveResult::Succeed(_)
+		));
+		// no finite failure, no answer either
+		assert_eq!(
+			theory.query("\\+ loop(a)", 32).unwrap().prove(),
+			ProveResult::DepthExceed
+		);
+		let mut solutions = theory.query("male(X), \\+ married(X, _)", 32).unwrap();
+		solutions.next().unwrap();
+		let proof = solutions.proof();
+		assert_eq!(proof.len(), 1);
+		assert_eq!(proof[0].goal.to_string(), "male(bob)");
+	}
+
+	#[test]
+	fn prove_cut() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"member(X, c(X, _)).
+			member(X, c(_, T)) :- member(X, T).
+			list(c(a, c(b, c(c, nil)))).
+			q(b).
+			q(c).
+			first(X) :- list(L), member(X, L), !.
+			first_q(X) :- list(L), member(X, L), q(X), !.
+			first_a(X) :- list(L), member(X, L), !, q(X).
+			pair(X, Y) :- first(X), list(L), member(Y, L).
+			kind(X, q) :- q(X), !.
+			kind(_, other).
+			barrier(X) :- !(0), q(X).
+			",
+			)
+			.unwrap();
+		let answers = |query, var| {
+			theory
+				.query(query, 32)
+				.unwrap()
+				.map(|answer: Answer| answer[var].to_string())
+				.collect::<Vec<String>>()
+		};
+		assert_eq!(answers("first(X)", "X"), vec!["a"]);
+		// reached after backtracking into member
+		assert_eq!(answers("first_q(X)", "X"), vec!["b"]);
+		// no retry of member once q(a) fails
+		assert!(answers("first_a(X)", "X").is_empty());
+		// the cut inside first does not prune its caller
+		assert_eq!(answers("pair(X, Y)", "Y"), vec!["a", "b", "c"]);
+		assert_eq!(answers("kind(b, K)", "K"), vec!["q"]);
+		assert_eq!(answers("kind(a, K)", "K"), vec!["other"]);
+		assert_eq!(answers("list(L), member(X, L), !", "X"), vec!["a"]);
+		// nor does a cut inside a negation
+		assert_eq!(
+			answers("list(L), member(X, L), \\+ \\+ first(X)", "X"),
+			vec!["a", "b", "c"]
+		);
+		let mut solutions = theory.query("kind(b, K)", 32).unwrap();
+		solutions.next().unwrap();
+		let proof = solutions.proof();
+		assert_eq!(proof[0].children.len(), 1);
+		assert_eq!(proof[0].children[0].goal.to_string(), "q(b)");
+		// !/1 written by users is an ordinary predicate, not a cut barrier
+		assert!(answers("!(a)", "X").is_empty());
+		assert!(answers("barrier(X)", "X").is_empty());
+	}
+
+	#[test]
+	fn prove_control() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"member(X, c(X, _)).
+			member(X, c(_, T)) :- member(X, T).
+			list(c(a, c(b, c(c, nil)))).
+			q(b).
+			q(c).
+			eq(X, X).
+			either(X) :- (q(X) ; eq(X, d)).
+			class(X, K) :- (q(X) -> eq(K, yes) ; eq(K, no)).
+			first_q(X) :- list(L), (member(X, L), q(X) -> true ; eq(X, none)).
+			only_q(X) :- list(L), member(X, L), (q(X) -> true).
+			all_q(X) :- list(L), (member(X, L), q(X) *-> true ; eq(X, none)).
+			no_z(X) :- list(L), (member(X, L), eq(X, z) *-> true ; eq(X, none)).
+			first_or_d(X) :- (list(L), member(X, L), ! ; eq(X, d)).
+			first_or_d(e).
+			local(X) :- (list(L), member(X, L), ! -> true).
+			local(e).
+			",
+			)
+			.unwrap();
+		let answers = |query| {
+			theory
+				.query(query, 32)
+				.unwrap()
+				.map(|answer: Answer| answer["X"].to_string())
+				.collect::<Vec<String>>()
+		};
+		assert_eq!(answers("either(X)"), vec!["b", "c", "d"]);
+		assert_eq!(answers("(q(X) ; eq(X, d)), \\+ eq(X, c)"), vec!["b", "d"]);
+		assert_eq!(answers("class(a, X)"), vec!["no"]);
+		assert_eq!(answers("class(b, X)"), vec!["yes"]);
+		// the condition commits to its first solution
+		assert_eq!(answers("first_q(X)"), vec!["b"]);
+		assert_eq!(answers("only_q(X)"), vec!["b", "c"]);
+		// the soft-cut keeps them all
+		assert_eq!(answers("all_q(X)"), vec!["b", "c"]);
+		assert_eq!(answers("no_z(X)"), vec!["none"]);
+		// the marker ending a condition cannot be written
+		assert!(answers("'$soft_cut'(a), eq(X, a)").is_empty());
+		// a cut in a branch cuts the clause, a cut in a condition only the condition
+		assert_eq!(answers("first_or_d(X)"), vec!["a"]);
+		assert_eq!(answers("local(X)"), vec!["a", "e"]);
+		let mut solutions = theory.query("class(b, K)", 32).unwrap();
+		solutions.next().unwrap();
+		let proof = solutions.proof();
+		let children: Vec<String> = proof[0]
+			.children
+			.iter()
+			.map(|x| x.goal.to_string())
+			.collect();
+		assert_eq!(children, vec!["q(b)", "eq(yes, yes)"]);
+	}
+
+	#[test]
+	fn prove_arithmetic() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"factorial(0, 1).
+			factorial(N, F) :- N > 0, N1 is N - 1, factorial(N1, F1), F is N * F1.
+			length(nil, 0).
+			length(c(_, T), N) :- length(T, N0), N is N0 + 1.
+			sign(X, S) :- (X < 0 -> S is -1 ; X =:= 0 -> S is 0 ; S is 1).
+			",
+			)
+			.unwrap();
+		let value = |query| match theory.query(query, 256).unwrap().prove() {
+			ProveResult::Succeed(answer) => answer["X"].to_string(),
+			result => panic!("{:?}", result),
+		};
+		assert_eq!(value("factorial(10, X)"), "3628800");
+		assert_eq!(value("factorial(25, X)"), "15511210043330985984000000");
+		assert_eq!(value("X is 2 / 6 + 1r2"), "5r6");
+		assert_eq!(value("length(c(a, c(b, c(c, nil))), X)"), "3");
+		assert_eq!(value("X is 7 mod -2 + abs(-3) * max(1, 2)"), "5");
+		assert_eq!(value("sign(-4, X)"), "-1");
+		assert_eq!(value("sign(4, X)"), "1");
+		assert_eq!(
+			theory.query("3 is 1 + 2, 2 < 1", 64).unwrap().prove(),
+			ProveResult::Fail
+		);
+		assert!(matches!(
+			theory
+				.query("3 is 1 + 2, 1 =< 1, 2 =\\= 1", 64)
+				.unwrap()
+				.prove(),
+			ProveResult::Succeed(_)
+		));
+		let error = match theory.query("factorial(N, 2)", 64).unwrap().prove() {
+			ProveResult::Error(error) => error,
+			result => panic!("{:?}", result),
+		};
+		assert_eq!(error.kind, ProveErrorKind::Instantiation);
+		assert_eq!(error.goal.get_name(), ">");
+		assert!(error.culprit.is_var());
+		let mut solutions = theory.query("X is foo + 1", 64).unwrap();
+		assert_eq!(solutions.next(), None);
+		assert_eq!(
+			solutions.error().unwrap().kind,
+			ProveErrorKind::NotEvaluable
+		);
+		assert_eq!(solutions.error().unwrap().culprit.to_string(), "foo");
+		// errors are not swallowed by negation
+		assert!(matches!(
+			theory.query("\\+ X > 1", 64).unwrap().prove(),
+			ProveResult::Error(_)
+		));
+	}
+
+	#[test]
+	fn add_string_comments() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"% facts. with periods.
+			/* weights, in kg.
+			   /* nested. */ */
+			weight(box, 2.5). % light.
+
+			%! heavy(?Item)
+			%  Items above 10 kg. See weight/2.
+			heavy(X) :- weight(X, W), W > 10.
+			/** Same as heavy/1. */
+			heavy(X, yes) :- heavy(X).
+			%! lifts(?Item)
+			lifts(X) :- \\+ heavy(X).
+			% no documentation, not even for lifts/1.
+			lifts(X) :- weight(X, _).
+			:- table heavy/1.
+			",
+			)
+			.unwrap();
+		assert_eq!(
+			theory.documentation("heavy", 1),
+			Some("heavy(?Item)\nItems above 10 kg. See weight/2.")
+		);
+		assert_eq!(theory.documentation("heavy", 2), Some("Same as heavy/1."));
+		assert_eq!(theory.documentation("lifts", 1), Some("lifts(?Item)"));
+		assert_eq!(theory.documentation("weight", 2), None);
+		assert!(theory.is_tabled(&Pred::compound(
+			Symbol::intern("heavy"),
+			vec![Pred::leaf(Term::Var(Var { id: 0, name: None }))]
+		)));
+		assert_eq!(theory.query("lifts(box)", 32).unwrap().count(), 2);
+		// comments inside a directive are layout too
+		theory
+			.add_string(":- table /* tabled */ tc/1.\n:- table tc2/1 % c\n.\n")
+			.unwrap();
+		let tabled = |string| {
+			let (goals, _) = Clause::goals_from_string(string, 0).unwrap();
+			theory.is_tabled(&goals[0])
+		};
+		assert!(tabled("tc(X)"));
+		assert!(tabled("tc2(X)"));
+		assert!(!tabled("tc(X, Y)"));
+	}
+
+	#[test]
+	fn prove_quoted() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"capital('New York', 'Albany').
+			capital('Île-de-France', 'Paris').
+			greeting(\"hi\").
+			:- set_prolog_flag(double_quotes, string).
+			motto(\"e pluribus unum.\").
+			",
+			)
+			.unwrap();
+		let answers: Vec<String> = theory
+			.query("capital(X, Y)", 32)
+			.unwrap()
+			.map(|answer| answer["X"].to_string())
+			.collect();
+		assert_eq!(answers, vec!["'New York'", "'Île-de-France'"]);
+		let value = |query| match theory.query(query, 32).unwrap().prove() {
+			ProveResult::Succeed(answer) => answer["X"].to_string(),
+			result => panic!("{:?}", result),
+		};
+		assert_eq!(value("capital('New York', X)"), "'Albany'");
+		assert_eq!(value("capital(X, 'Paris')"), "'Île-de-France'");
+		// read before the flag changed
+		assert_eq!(value("greeting(X)"), "[104, 105]");
+		assert_eq!(value("motto(X)"), "\"e pluribus unum.\"");
+		assert!(matches!(
+			theory
+				.query("greeting(`hi`), motto(\"e pluribus unum.\")", 32)
+				.unwrap()
+				.prove(),
+			ProveResult::Succeed(_)
+		));
+		assert_eq!(value("compare(X, 'Zurich', abc)"), "<");
+		assert_eq!(value("compare(X, 'New York', 'Albany')"), ">");
+		assert_eq!(value("compare(X, \"abc\", zzz)"), ">");
+		assert_eq!(
+			theory.query("capital(albany, X)", 32).unwrap().prove(),
+			ProveResult::Fail
+		);
+		let errors = theory
+			.add_string(":- set_prolog_flag(double_quotes, bytes).")
+			.unwrap_err();
+		assert_eq!(errors[0].kind, ParseErrorKind::InvalidDirective);
+	}
+
+	#[test]
+	fn prove_lists() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				":- use_module(library(lists)).
+			sum([], 0).
+			sum([H|T], S) :- sum(T, S0), S is S0 + H.
+			",
+			)
+			.unwrap();
+		let value = |query| match theory.query(query, 256).unwrap().prove() {
+			ProveResult::Succeed(answer) => answer["X"].to_string(),
+			result => panic!("{:?}", result),
+		};
+		let all = |query| -> Vec<String> {
+			theory
+				.query(query, 256)
+				.unwrap()
+				.map(|answer| answer["X"].to_string())
+				.collect()
+		};
+		assert_eq!(value("append([a, b], [c], X)"), "[a, b, c]");
+		assert_eq!(all("append(X, Y, [a, b])"), vec!["[]", "[a]", "[a, b]"]);
+		assert_eq!(all("member(X, [a, f(b), [c]])"), vec!["a", "f(b)", "[c]"]);
+		assert_eq!(value("length([a, b, c], X)"), "3");
+		assert_eq!(all("length(X, 2), append(X, [], [a, b])"), vec!["[a, b]"]);
+		assert_eq!(value("reverse([1, 2, 3], X)"), "[3, 2, 1]");
+		assert_eq!(value("nth0(1, [a, b, c], X)"), "b");
+		assert_eq!(value("nth1(1, [a, b, c], X)"), "a");
+		assert_eq!(all("nth1(X, [a, b, a], a)"), vec!["1", "3"]);
+		assert_eq!(value("last([a, b, c], X)"), "c");
+		assert_eq!(
+			value("msort([b, 2, a, 1.0, f(x), b], X)"),
+			"[1.0, 2, a, b, b, f(x)]"
+		);
+		assert_eq!(
+			value("sort([b, 2, a, 1.0, f(x), b], X)"),
+			"[1.0, 2, a, b, f(x)]"
+		);
+		assert_eq!(
+			all("permutation([1, 2, 3], X)"),
+			vec![
+				"[1, 2, 3]",
+				"[1, 3, 2]",
+				"[2, 1, 3]",
+				"[2, 3, 1]",
+				"[3, 1, 2]",
+				"[3, 2, 1]"
+			]
+		);
+		assert_eq!(value("sum([1, 2, 3.5], X)"), "6.5");
+		assert!(matches!(
+			theory
+				.query(
+					"is_list([a]), \\+ is_list([a|_]), atom(a), \\+ atom(1), var(_)",
+					32
+				)
+				.unwrap()
+				.prove(),
+			ProveResult::Succeed(_)
+		));
+		{
+			let mut solutions = theory.query("msort([b|T], X)", 32).unwrap();
+			assert_eq!(solutions.next(), None);
+			assert_eq!(
+				solutions.error().unwrap().kind,
+				ProveErrorKind::Instantiation
+			);
+		}
+		// loading twice does not duplicate clauses
+		assert_eq!(theory.use_library("lists"), Ok(true));
+		assert_eq!(theory.use_library("strings"), Ok(false));
+		assert_eq!(theory.query("member(X, [a])", 32).unwrap().count(), 1);
+		// the library is read with the standard operators
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(":- op(0, yfx, +).\n:- use_module(library(lists)).")
+			.unwrap();
+		match theory.query("length([a, b], N)", 32).unwrap().prove() {
+			ProveResult::Succeed(answer) => assert_eq!(answer["N"].to_string(), "2"),
+			result => panic!("{:?}", result),
+		}
+	}
+
+	#[test]
+	fn prove_operators() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				":- op(700, xfx, likes).
+			:- op(200, xfy, [and, or]).
+			mary likes wine.
+			mary likes X and Y :- mary likes X, mary likes Y.
+			john likes X :- mary likes X.
+			",
+			)
+			.unwrap();
+		let value = |query| match theory.query(query, 32).unwrap().prove() {
+			ProveResult::Succeed(answer) => theory.term_to_string(&answer["X"]),
+			result => panic!("{:?}", result),
+		};
+		assert_eq!(value("john likes X"), "wine");
+		assert_eq!(value("X = f(Y), Y = 1 + 2"), "f(1 + 2)");
+		assert_eq!(value("X = (a and b or c)"), "a and b or c");
+		assert_eq!(value("X = ((a and b) and c)"), "(a and b) and c");
+		assert_eq!(value("X = wine and wine, mary likes X"), "wine and wine");
+		let succeeds = |theory: &Theory, query| {
+			matches!(
+				theory.query(query, 32).unwrap().prove(),
+				ProveResult::Succeed(_)
+			)
+		};
+		assert!(succeeds(&theory, "a \\= b, \\+ f(_) \\= f(1)"));
+		assert!(!succeeds(&theory, "f(X, b) = f(a, X)"));
+		match theory.query("X = a and b", 32).unwrap().prove() {
+			ProveResult::Succeed(answer) => assert_eq!(answer["X"].to_string(), "and(a, b)"),
+			result => panic!("{:?}", result),
+		}
+		let errors = theory
+			.add_string(
+				":- op(1201, xfx, foo).
+			:- op(700, abc, foo).
+			:- op(700, xfx, [foo, ',']).
+			:- op(700, xfx, X).
+			:- op(700, xf, likes).
+			",
+			)
+			.unwrap_err();
+		assert_eq!(errors.len(), 5);
+		assert!(errors
+			.iter()
+			.all(|error| error.kind == ParseErrorKind::InvalidDirective));
+		assert!(theory.query("a foo b", 32).is_err());
+		assert!(theory.op(700, Fixity::Xfx, "foo"));
+		assert!(succeeds(&theory, "X = (a foo b), X = foo(_, b)"));
+	}
+
+	#[test]
+	fn prove_floats() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"reading(a, 3.14).
+			reading(b, 2.5e-1).
+			reading(c, 2).
+			scaled(S, X) :- reading(S, R), X is R * 1000.
+			",
+			)
+			.unwrap();
+		let value = |query| match theory.query(query, 64).unwrap().prove() {
+			ProveResult::Succeed(answer) => answer["X"].to_string(),
+			result => panic!("{:?}", result),
+		};
+		assert_eq!(value("scaled(a, X)"), "3140.0");
+		assert_eq!(value("scaled(b, X)"), "250.0");
+		assert_eq!(value("scaled(c, X)"), "2000");
+		assert_eq!(value("X is truncate(sqrt(2) * 100)"), "141");
+		assert_eq!(value("compare(X, 1, 1.0)"), ">");
+		assert_eq!(value("compare(X, 1.5, 2)"), "<");
+		assert_eq!(value("compare(X, f(b), g(a))"), "<");
+		assert_eq!(value("compare(X, f(a, b), g(a))"), ">");
+		assert!(matches!(
+			theory
+				.query(
+					"1 =:= 1.0, 1 \\== 1.0, 1.0 @< 1, Y @< 1, 2 @< a, a @< f(a), f(a) == f(a)",
+					64
+				)
+				.unwrap()
+				.prove(),
+			ProveResult::Succeed(_)
+		));
+		assert_eq!(
+			theory
+				.query("reading(a, 3.14) , 1 == 1.0", 64)
+				.unwrap()
+				.prove(),
+			ProveResult::Fail
+		);
+	}
+
+	#[test]
+	fn prove_occurs_check() {
+		let mut theory: Theory = Default::default();
+		theory.add_string("loop(X, f(X)).").unwrap();
+		let result = |theory: &Theory, query| theory.query(query, 64).unwrap().prove();
+		match result(&theory, "loop(Y, Y)") {
+			ProveResult::Succeed(answer) => {
+				assert_eq!(answer["Y"].to_string(), "_S1 where _S1 = f(_S1)")
+			}
+			result => panic!("{:?}", result),
+		}
+		assert_eq!(
+			result(&theory, "unify_with_occurs_check(X, f(X))"),
+			ProveResult::Fail
+		);
+		assert!(matches!(
+			result(&theory, "unify_with_occurs_check(X, f(Y)), X = f(X)"),
+			ProveResult::Succeed(_)
+		));
+		theory
+			.add_string(":- set_prolog_flag(occurs_check, true).")
+			.unwrap();
+		assert_eq!(result(&theory, "loop(Y, Y)"), ProveResult::Fail);
+		assert_eq!(result(&theory, "X = f(X)"), ProveResult::Fail);
+		assert!(matches!(
+			result(&theory, "X \\= f(X), msort([b, a], X)"),
+			ProveResult::Succeed(_)
+		));
+		theory.set_occurs_check(OccursCheck::Error);
+		match result(&theory, "loop(Y, Y)") {
+			ProveResult::Error(error) => {
+				assert_eq!(error.kind, ProveErrorKind::OccursCheck);
+				assert_eq!(error.culprit.to_string(), "f(Y)");
+			}
+			result => panic!("{:?}", result),
+		}
+		assert!(matches!(
+			result(&theory, "X = Y, Y = f(Z)"),
+			ProveResult::Succeed(_)
+		));
+		assert!(matches!(
+			result(&theory, "X = f(Y), Y = g(X)"),
+			ProveResult::Error(_)
+		));
+		assert!(theory
+			.add_string(":- set_prolog_flag(occurs_check, maybe).")
+			.is_err());
+	}
+
+	#[test]
+	fn prove_cyclic_terms() {
+		let theory: Theory = Default::default();
+		let result = |query| theory.query(query, 64).unwrap().prove();
+		match result("X = f(X), Y = f(Y), X = Y, compare(O, X, f(Y)), Z = g(X)") {
+			ProveResult::Succeed(answer) => {
+				assert_eq!(answer["X"].to_string(), "_S1 where _S1 = f(_S1)");
+				assert_eq!(answer["O"].to_string(), "=");
+				assert_eq!(answer["Z"].to_string(), "g(_S1) where _S1 = f(_S1)");
+			}
+			result => panic!("{:?}", result),
+		}
+		assert!(matches!(
+			result("X = [a|X], X == [a, a|X], \\+ is_list(X)"),
+			ProveResult::Succeed(_)
+		));
+		assert_eq!(result("X = f(X), Y = f(f(a)), X = Y"), ProveResult::Fail);
+		match result("X = X + 1, Y is X") {
+			ProveResult::Error(error) => assert_eq!(error.kind, ProveErrorKind::NotEvaluable),
+			result => panic!("{:?}", result),
+		}
+	}
+
+	#[test]
+	fn add_string_errors() {
+		let mut theory: Theory = Default::default();
+		let errors = theory
+			.add_string(
+				"father(a, b).
+father(b, 2c).
+parent(X, Y) :- father(X, Y).
+parent(X, Y :- mother(X, Y).
+",
+			)
+			.unwrap_err();
+		assert_eq!(errors.len(), 2);
+		assert_eq!(errors[0].kind, ParseErrorKind::InvalidIdentifier);
+		assert_eq!(errors[0].clause, "\nfather(b, 2c)");
+		assert_eq!((errors[0].span.line, errors[0].span.column), (2, 11));
+		assert_eq!(errors[1].kind, ParseErrorKind::UnmatchedLeftParenthesis);
+		assert_eq!((errors[1].span.line, errors[1].span.column), (4, 7));
+		// valid clauses are still added
+		assert!(matches!(
+			theory.query("parent(a, b)", 32).unwrap().prove(),
+			ProveResult::Succeed(_)
+		));
+		let errors = theory
+			.add_string("mother(c, d). size(2.5).\nmother(d, e)")
+			.unwrap_err();
+		assert_eq!(errors.len(), 1);
+		assert_eq!(errors[0].kind, ParseErrorKind::UnterminatedClause);
+		assert_eq!((errors[0].span.line, errors[0].span.column), (1, 25));
+		let errors = theory
+			.add_string("été(x). r({b}).\np(a) :- q(a) }.")
+			.unwrap_err();
+		assert_eq!(errors.len(), 3);
+		assert!(errors
+			.iter()
+			.all(|error| error.kind == ParseErrorKind::InvalidCharacter));
+		assert_eq!((errors[2].span.line, errors[2].span.column), (2, 14));
+		assert_eq!(theory.query("q(X)", 32).unwrap().prove(), ProveResult::Fail);
+		match theory.add_reader("mother(e, f).".as_bytes()) {
+			Ok(()) => {}
+			Err(error) => panic!("{}", error),
+		}
+		assert_eq!(theory.query("mother(X, Y)", 32).unwrap().count(), 2);
+	}
 }
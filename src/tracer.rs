@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use crate::clause::Clause;
+use crate::error::ProveError;
+use crate::pred::instmap_to_string;
+use crate::pred::{InstMap, Pred};
+use crate::theory::Answer;
+
+// observer of the search, every callback defaults to doing nothing
+pub trait Tracer {
+	// a target is tried for the first time, targets is the whole goal list
+	fn call(&mut self, _target: &Pred, _targets: &VecDeque<Pred>) {}
+
+	fn matched(&mut self, _target: &Pred, _clause: &Clause, _instmap: &InstMap) {}
+
+	// no more clause for the target, the search backtracks
+	fn fail(&mut self, _target: &Pred) {}
+
+	fn depth_exceed(&mut self, _target: &Pred) {}
+
+	fn succeed(&mut self, _answer: &Answer) {}
+
+	// the query is aborted
+	fn error(&mut self, _error: &ProveError) {}
+
+	// false if every callback does nothing, the search then skips resolving the
+	// goals and bindings they are passed
+	fn enabled(&self) -> bool {
+		true
+	}
+}
+
+impl<T: Tracer + ?Sized> Tracer for &mut T {
+	fn call(&mut self, target: &Pred, targets: &VecDeque<Pred>) {
+		(**self).call(target, targets)
+	}
+
+	fn matched(&mut self, target: &Pred, clause: &Clause, instmap: &InstMap) {
+		(**self).matched(target, clause, instmap)
+	}
+
+	fn fail(&mut self, target: &Pred) {
+		(**self).fail(target)
+	}
+
+	fn depth_exceed(&mut self, target: &Pred) {
+		(**self).depth_exceed(target)
+	}
+
+	fn succeed(&mut self, answer: &Answer) {
+		(**self).succeed(answer)
+	}
+
+	fn error(&mut self, error: &ProveError) {
+		(**self).error(error)
+	}
+
+	fn enabled(&self) -> bool {
+		(**self).enabled()
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoTracer;
+
+impl Tracer for NoTracer {
+	fn enabled(&self) -> bool {
+		false
+	}
+}
+
+// colored step by step output on stdout
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConsoleTracer;
+
+impl Tracer for ConsoleTracer {
+	fn matched(&mut self, target: &Pred, clause: &Clause, instmap: &InstMap) {
+		println!("[32mMATCH[0m {} [32mto[0m {}", target, clause);
+		println!("[32mwith[0m {}", instmap_to_string(instmap));
+	}
+
+	fn fail(&mut self, _target: &Pred) {
+		println!("[31mFAIL[0m");
+	}
+
+	fn depth_exceed(&mut self, _target: &Pred) {
+		println!("[31mDEEP[0m");
+	}
+
+	fn succeed(&mut self, _answer: &Answer) {
+		println!("[36mCLEAR[0m");
+	}
+
+	fn error(&mut self, error: &ProveError) {
+		println!("[31mERROR[0m {}", error);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::theory::Theory;
+
+	#[derive(Default)]
+	struct Recorder {
+		events: Vec<String>,
+	}
+
+	impl Tracer for Recorder {
+		fn call(&mut self, target: &Pred, _targets: &VecDeque<Pred>) {
+			self.events.push(format!("call {}", target));
+		}
+
+		fn matched(&mut self, _target: &Pred, clause: &Clause, _instmap: &InstMap) {
+			self.events.push(format!("match {}", clause));
+		}
+
+		fn fail(&mut self, target: &Pred) {
+			self.events.push(format!("fail {}", target));
+		}
+
+		fn depth_exceed(&mut self, target: &Pred) {
+			self.events.push(format!("deep {}", target));
+		}
+
+		fn succeed(&mut self, _answer: &Answer) {
+			self.events.push("succeed".to_string());
+		}
+	}
+
+	#[test]
+	fn tracer_events() {
+		let mut theory: Theory = Default::default();
+		theory
+			.add_string(
+				"parent(X, Y) :- father(X, Y).
+		father(a, b).
+		loop(X) :- loop(X).
+		",
+			)
+			.unwrap();
+		let mut recorder: Recorder = Default::default();
+		theory
+			.query("parent(a, b)", 32)
+			.unwrap()
+			.with_tracer(&mut recorder)
+			.for_each(drop);
+		assert_eq!(
+			recorder.events,
+			vec![
+				"call parent(a, b)",
+				"match parent(X, Y) :- father(X, Y)",
+				"call father(a, b)",
+				"match father(a, b)",
+				"succeed",
+				"fail father(a, b)",
+				"fail parent(a, b)",
+			]
+		);
+		let mut recorder: Recorder = Default::default();
+		theory
+			.query("loop(a)", 2)
+			.unwrap()
+			.with_tracer(&mut recorder)
+			.for_each(drop);
+		assert_eq!(
+			recorder
+				.events
+				.iter()
+				.filter(|x| x.starts_with("deep"))
+				.count(),
+			1
+		);
+	}
+}